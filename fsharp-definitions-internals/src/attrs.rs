@@ -0,0 +1,450 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::fs_type::fs_type_check;
+use crate::model::UnionFields;
+
+use super::{ast, ident_from_str, Ctxt};
+use quote::{quote, ToTokens};
+
+use proc_macro2::TokenStream;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{Attribute, Ident, Lit, Meta, /* MetaList,*/ MetaNameValue, NestedMeta};
+
+// This is a helper to allow us to parse attributes
+struct AttrT(Vec<Attribute>);
+
+impl Parse for AttrT {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(AttrT(input.call(Attribute::parse_outer)?))
+    }
+}
+
+#[derive(Debug)]
+pub struct Attrs {
+    /// doc comment lines, blocks from different attribute lists separated by an empty line
+    pub doc: Vec<String>,
+    pub fs_type: Option<String>,
+    /// `#[fs(handler_name = "...")]`: a record of functions, one per variant, with a `dispatch`
+    pub handler_name: Option<String>,
+    /// `#[fs(handler_return = "...")]`: the (fsharp) type the handler functions return
+    pub handler_return: Option<String>,
+    /// `#[fs(factory_name = "...")]`: a module with a constructor function for each variant
+    pub factory_name: Option<String>,
+    /// `#[fs(factory_return_name = "...")]`: the (fsharp) type the constructors return
+    pub factory_return_name: Option<String>,
+    /// `#[fs(fs_as = "...")]`: the rust type this field (or, on a container, the whole type)
+    /// serializes like. `ts_as` is the deprecated spelling.
+    pub fs_as: Option<syn::Type>,
+    /// `#[fs(widen_numbers)]`: render all integers as `int64` and floats as `float`
+    pub widen_numbers: bool,
+    /// `#[fs(rename_all = "camelCase")]`: casing of record labels
+    pub rename_all: Option<LabelCase>,
+    /// `#[fs(union_fields = "inline")]`: how variant content appears in union cases
+    pub union_fields: Option<UnionFields>,
+}
+
+/// How a rust field identifier is turned into a fsharp record label
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelCase {
+    /// `content_type` => `contentType`
+    Camel,
+    /// `content_type` => `ContentType`
+    Pascal,
+    /// `content_type` => `content_type`
+    Snake,
+}
+
+impl LabelCase {
+    fn from_str(v: &str) -> Option<Self> {
+        match v {
+            "camelCase" => Some(LabelCase::Camel),
+            "PascalCase" => Some(LabelCase::Pascal),
+            "snake_case" => Some(LabelCase::Snake),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, ident: &str) -> String {
+        let ident = ident.trim_start_matches("r#");
+        if self == LabelCase::Snake {
+            return ident.to_string();
+        }
+        // keep any leading underscores
+        let trimmed = ident.trim_start_matches('_');
+        let mut label = ident[..ident.len() - trimmed.len()].to_string();
+        for (i, word) in trimmed.split('_').filter(|w| !w.is_empty()).enumerate() {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                if i == 0 && self == LabelCase::Camel {
+                    label.extend(first.to_lowercase());
+                } else {
+                    label.extend(first.to_uppercase());
+                }
+                label.push_str(chars.as_str());
+            }
+        }
+        label
+    }
+}
+
+#[inline]
+fn path_to_str(path: &syn::Path) -> String {
+    quote!(#path).to_string()
+}
+
+#[allow(unused)]
+pub fn turbofish_check(v: &str) -> Result<TokenStream, String> {
+    match v.parse::<proc_macro2::TokenStream>() {
+        // just get LexError as error message... so make our own.
+        Err(_) => Err(format!("Can't lex turbofish \"{}\"", v)),
+        Ok(tokens) => match syn::parse2::<syn::DeriveInput>(quote!( struct S{ a:v#tokens} )) {
+            Err(_) => Err(format!("Can't parse turbofish \"{}\"", v)),
+            Ok(_) => Ok(tokens),
+        },
+    }
+}
+impl Attrs {
+    pub fn new() -> Attrs {
+        Attrs {
+            doc: vec![],
+            // turbofish: None,
+            fs_type: None,
+            handler_name: None,
+            handler_return: None,
+            factory_name: None,
+            factory_return_name: None,
+            fs_as: None,
+            widen_numbers: false,
+            rename_all: None,
+            union_fields: None,
+        }
+    }
+    pub fn push_doc_comment(&mut self, attrs: &[Attribute]) {
+        if !self.doc.is_empty() {
+            self.doc.push(String::new());
+        }
+        self.doc.extend(Attrs::to_comments(attrs));
+    }
+
+    fn to_comments(attrs: &[Attribute]) -> Vec<String> {
+        attrs
+            .iter()
+            .filter_map(|attr| {
+                if path_to_str(&attr.path) == "doc" {
+                    attr.parse_meta().ok()
+                } else {
+                    None
+                }
+            })
+            .filter_map(|attr| {
+                use Lit::*;
+                use Meta::*;
+                if let NameValue(MetaNameValue { lit: Str(s), .. }) = attr {
+                    let value = s.value();
+                    let text = value
+                        .trim_start_matches("//!")
+                        .trim_start_matches("///")
+                        .trim_start_matches("/*!")
+                        .trim_start_matches("/**")
+                        .trim_end_matches("*/")
+                        .trim();
+                    if text.is_empty() {
+                        None
+                    } else {
+                        Some(text.to_string())
+                    }
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn err_msg<A: ToTokens>(&self, tokens: A, msg: String, ctxt: &Ctxt) {
+        ctxt.error_spanned_by(tokens, msg);
+    }
+    pub fn find_fsharp<'a>(
+        attrs: &'a [Attribute],
+        ctxt: &'a Ctxt,
+    ) -> impl Iterator<Item = Meta> + 'a {
+        use syn::Meta::*;
+        use NestedMeta::*;
+
+        fn err<A: quote::ToTokens>(tokens: A, msg: String, ctxt: &Ctxt) {
+            ctxt.error_spanned_by(tokens, format!("invalid fsharp syntax: {}", msg));
+        }
+
+        attrs
+            .iter()
+            .filter_map(move |attr| match path_to_str(&attr.path).as_ref() {
+                "fs" => match attr.parse_meta() {
+                    Ok(v) => Some(v),
+                    Err(msg) => {
+                        err(attr, msg.to_string(), ctxt);
+                        None
+                    }
+                },
+                _ => None,
+            })
+            .filter_map(move |m| match m {
+                List(l) => Some(l.nested),
+                ref tokens => {
+                    err(&m, quote!(#tokens).to_string(), ctxt);
+                    None
+                }
+            })
+            .flatten()
+            .filter_map(move |m| match m {
+                Meta(m) => Some(m),
+                ref tokens => {
+                    err(&m, quote!(#tokens).to_string(), ctxt);
+                    None
+                }
+            })
+    }
+    pub fn push_attrs(&mut self, _struct_ident: &Ident, attrs: &[Attribute], ctxt: &Ctxt) {
+        use syn::Meta::*;
+        use Lit::*;
+        // use NestedMeta::*;
+
+        for attr in Self::find_fsharp(attrs, ctxt) {
+            match attr {
+                // #[fs(handler_name = "HandleFooBar")]
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "handler_name") => {
+                    self.handler_name = Some(value.value())
+                }
+                // #[fs(handler_return = "bool")]
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "handler_return") => {
+                    let v = value.value();
+                    match fs_type_check(&v) {
+                        Ok(()) => self.handler_return = Some(v),
+                        Err(msg) => self.err_msg(
+                            &attr,
+                            format!(
+                                "handler_return: \"{}\" is not a valid fsharp type: {}",
+                                v, msg
+                            ),
+                            ctxt,
+                        ),
+                    }
+                }
+                // #[fs(factory_name = "FooBar")]
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "factory_name") => {
+                    self.factory_name = Some(value.value())
+                }
+                // #[fs(factory_return_name = "FooBar")]
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "factory_return_name") => {
+                    let v = value.value();
+                    match fs_type_check(&v) {
+                        Ok(()) => self.factory_return_name = Some(v),
+                        Err(msg) => self.err_msg(
+                            &attr,
+                            format!(
+                                "factory_return_name: \"{}\" is not a valid fsharp type: {}",
+                                v, msg
+                            ),
+                            ctxt,
+                        ),
+                    }
+                }
+                // #[fs(widen_numbers)]
+                Path(ref path) if is_path_ident(path, "widen_numbers") => self.widen_numbers = true,
+                // #[fs(rename_all = "camelCase")]
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "rename_all") => match LabelCase::from_str(&value.value()) {
+                    Some(case) => self.rename_all = Some(case),
+                    None => self.err_msg(
+                        &attr,
+                        format!(
+                            "rename_all: \"{}\" must be one of \"camelCase\", \"PascalCase\" or \"snake_case\"",
+                            value.value()
+                        ),
+                        ctxt,
+                    ),
+                },
+                // #[fs(union_fields = "inline")]
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "union_fields") => match value.value().as_ref() {
+                    "alias" => self.union_fields = Some(UnionFields::Alias),
+                    "inline" => self.union_fields = Some(UnionFields::Inline),
+                    "anonymous" => self.union_fields = Some(UnionFields::Anonymous),
+                    v => self.err_msg(
+                        &attr,
+                        format!(
+                            "union_fields: \"{}\" must be one of \"alias\", \"inline\" or \"anonymous\"",
+                            v
+                        ),
+                        ctxt,
+                    ),
+                },
+                // #[fs(fs_as = "String")], `ts_as` is deprecated (see `deprecated_ts_as`)
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "fs_as") || is_path_ident(path, "ts_as") => {
+                    let name = if is_path_ident(path, "fs_as") {
+                        "fs_as"
+                    } else {
+                        "ts_as"
+                    };
+                    self.push_fs_as(&attr, name, value, ctxt)
+                }
+                ref i @ NameValue(..) | ref i @ List(..) | ref i @ Path(..) => {
+                    self.err_msg(i, format!("unsupported option: {}", quote!(#i)), ctxt);
+                }
+            }
+        }
+    }
+    pub fn push_field_attrs(&mut self, _struct_ident: &Ident, attrs: &[Attribute], ctxt: &Ctxt) {
+        use syn::Meta::*;
+        use Lit::*;
+        // use NestedMeta::*;
+
+        for attr in Self::find_fsharp(attrs, ctxt) {
+            match attr {
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "fs_type") => {
+                    let v = value.value();
+                    match fs_type_check(&v) {
+                        Ok(()) => self.fs_type = Some(v),
+                        Err(msg) => {
+                            self.err_msg(
+                                attr,
+                                format!("fs_type: \"{}\" is not a valid fsharp type: {}", v, msg),
+                                ctxt,
+                            );
+                        }
+                    }
+                }
+                // #[fs(fs_as = "Cow<str>")], `ts_as` is deprecated (see `deprecated_ts_as`)
+                NameValue(MetaNameValue {
+                    ref path,
+                    lit: Str(ref value),
+                    ..
+                }) if is_path_ident(path, "fs_as") || is_path_ident(path, "ts_as") => {
+                    let name = if is_path_ident(path, "fs_as") {
+                        "fs_as"
+                    } else {
+                        "ts_as"
+                    };
+                    self.push_fs_as(&attr, name, value, ctxt)
+                }
+                // #[fs(widen_numbers)]
+                Path(ref path) if is_path_ident(path, "widen_numbers") => self.widen_numbers = true,
+
+                ref i @ NameValue(..) | ref i @ List(..) | ref i @ Path(..) => {
+                    self.err_msg(i, format!("unsupported option: {}", quote!(#i)), ctxt);
+                }
+            }
+        }
+    }
+
+    fn push_fs_as(&mut self, attr: &syn::Meta, name: &str, value: &syn::LitStr, ctxt: &Ctxt) {
+        let v = value.value();
+        match syn::parse_str::<syn::Type>(&v) {
+            Ok(t) => self.fs_as = Some(t),
+            Err(..) => self.err_msg(
+                attr,
+                format!("{}: \"{}\" is not a valid rust type", name, v),
+                ctxt,
+            ),
+        }
+    }
+
+    pub fn from_variant(variant: &ast::Variant) -> Attrs {
+        let mut res = Self::new();
+        res.push_doc_comment(&variant.original.attrs);
+        res
+    }
+
+    pub fn from_field_doc(field: &ast::Field) -> Attrs {
+        let mut res = Self::new();
+        res.push_doc_comment(&field.original.attrs);
+        res
+    }
+
+    pub fn from_field(field: &ast::Field, ctxt: &Ctxt) -> Attrs {
+        let mut res = Self::new();
+        if let Some(ref ident) = field.original.ident {
+            res.push_field_attrs(ident, &field.original.attrs, ctxt);
+        } else {
+            let id = ident_from_str("unnamed");
+            res.push_field_attrs(&id, &field.original.attrs, ctxt);
+        }
+        res.push_doc_comment(&field.original.attrs);
+        res
+    }
+}
+
+fn is_path_ident(path: &syn::Path, test: &str) -> bool {
+    if let Some(ref ident) = path.get_ident() {
+        format!("{}", ident) == *test
+    } else {
+        false
+    }
+}
+
+/// Where the deprecated `#[fs(ts_as = "...")]` is used on the type, its
+/// variants or fields. The derives turn these into warnings.
+pub fn deprecated_ts_as(input: &syn::DeriveInput) -> Vec<proc_macro2::Span> {
+    let mut attrs = input.attrs.iter().collect::<Vec<_>>();
+    match input.data {
+        syn::Data::Struct(ref data) => attrs.extend(data.fields.iter().flat_map(|f| &f.attrs)),
+        syn::Data::Enum(ref data) => {
+            for variant in &data.variants {
+                attrs.extend(&variant.attrs);
+                attrs.extend(variant.fields.iter().flat_map(|f| &f.attrs));
+            }
+        }
+        syn::Data::Union(_) => (),
+    }
+    attrs
+        .into_iter()
+        .filter(|attr| attr.path.is_ident("fs"))
+        // badly formed attributes are reported by the parse
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.path.is_ident("ts_as") => {
+                Some(nv.path.span())
+            }
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,265 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::{filter_visible, ParseContext};
+use crate::attrs::Attrs;
+use crate::fs_ident::fs_case_ident;
+use crate::model::{
+    Case, CaseFields, Factory, Handler, Primitive, Tagging, TypeBody, TypeRef, Union, UnionFields,
+};
+use serde_derive_internals::{ast, attr::TagType};
+const CONTENT: &str = "fields"; // default content tag
+                                // const TAG: &'static str = "kind"; // default tag tag
+
+struct TagInfo<'a> {
+    /// #[serde(tag = "...")]
+    tag: Option<&'a str>,
+    /// #[serde(content = "...")]
+    content: Option<&'a str>,
+    /// flattened without tag `{ "key1": "", "key2": "" }`
+    untagged: bool,
+}
+
+impl<'a> TagInfo<'a> {
+    fn from_enum(e: &'a TagType) -> Self {
+        match e {
+            TagType::Internal { tag, .. } => TagInfo {
+                tag: Some(tag),
+                content: None,
+                untagged: false,
+            },
+            TagType::Adjacent { tag, content, .. } => TagInfo {
+                tag: Some(tag),
+                content: Some(content),
+                untagged: false,
+            },
+            TagType::External => TagInfo {
+                tag: None,
+                content: None,
+                untagged: false,
+            },
+            TagType::None => TagInfo {
+                tag: None,
+                content: None,
+                untagged: true,
+            },
+        }
+    }
+}
+
+#[allow(clippy::or_fun_call, clippy::bind_instead_of_map)]
+impl<'a> ParseContext<'_> {
+    pub(crate) fn derive_enum(
+        &self,
+        variants: &[ast::Variant<'a>],
+        ast_container: &ast::Container,
+    ) -> TypeBody {
+        // https://serde.rs/enum-representations.html
+        let taginfo = TagInfo::from_enum(ast_container.attrs.tag());
+        // remove skipped ( check for #[serde(skip)] )
+        let variants: Vec<&ast::Variant<'a>> = variants
+            .iter()
+            .filter(|v| !v.attrs.skip_serializing())
+            .collect();
+        let case_names = variants
+            .iter()
+            .map(|v| (fs_case_ident(&v.ident.to_string()), &v.ident))
+            .collect::<Vec<_>>();
+        self.check_unique_names("fsharp union case", &case_names);
+
+        // all unit variant enums are rendered as fieldless unions, not fsharp
+        // enums (`| Red = 0`): serde_json writes them by name (`"Red"`) which is
+        // what FSharp.Json does for union cases without fields.
+
+        let cases: Vec<Case> = variants
+            .iter()
+            .map(|variant| Case {
+                name: variant.ident.to_string(),
+                serde_name: variant.attrs.name().serialize_name(),
+                doc: Attrs::from_variant(variant).doc,
+                fields: match variant.style {
+                    ast::Style::Struct => self.derive_struct_variant(&taginfo, &variant.fields),
+                    ast::Style::Newtype => {
+                        self.derive_newtype_variant(&taginfo, &variant.fields[0])
+                    }
+                    ast::Style::Tuple => self.derive_tuple_variant(&taginfo, &variant.fields),
+                    ast::Style::Unit => CaseFields::Unit,
+                },
+            })
+            .collect();
+
+        TypeBody::Union(Union {
+            tagging: match (taginfo.tag, taginfo.content) {
+                (Some(tag), Some(content)) => Tagging::Adjacent {
+                    tag: tag.to_string(),
+                    content: content.to_string(),
+                },
+                (Some(tag), None) => Tagging::Internal {
+                    tag: tag.to_string(),
+                },
+                (None, None) if taginfo.untagged => Tagging::Untagged,
+                (None, None) => Tagging::External,
+                (None, Some(_)) => {
+                    // `TagInfo::from_enum` never gives a content without a tag
+                    self.err_msg(&self.ident, "`content` requires a `tag`");
+                    Tagging::External
+                }
+            },
+            handler: self.handler(),
+            factory: self.factory(&cases),
+            union_fields: self.union_fields(&taginfo, &variants, &cases),
+            cases,
+        })
+    }
+
+    /// `#[fs(factory_name = "...", factory_return_name = "...")]`, either of which
+    /// asks for a factory: `EnumFactory` returning the enum by default.
+    fn factory(&self, cases: &[Case]) -> Option<Factory> {
+        let attrs = &self.global_attrs;
+        if attrs.factory_name.is_none() && attrs.factory_return_name.is_none() {
+            return None;
+        }
+        // the fields of a flattened struct aren't known until the sections are organized
+        let flattens_struct = cases.iter().any(|case| match case.fields {
+            CaseFields::Struct(ref fields) => fields
+                .iter()
+                .any(|f| f.flatten && matches!(f.ty, TypeRef::Named { .. })),
+            _ => false,
+        });
+        if flattens_struct {
+            self.err_msg(
+                &self.ident,
+                &format!(
+                    "{}: a factory can't construct variants with #[serde(flatten)] structs",
+                    self.ident
+                ),
+            );
+        }
+        Some(Factory {
+            name: attrs
+                .factory_name
+                .clone()
+                .unwrap_or_else(|| format!("{}Factory", self.ident)),
+            return_type: attrs.factory_return_name.clone(),
+        })
+    }
+
+    /// `#[fs(union_fields = "...")]`: only aliases can carry the FSharp.Json
+    /// attributes of flattened and base64 fields, and inline fields only
+    /// have a JSON shape without a tag.
+    fn union_fields(
+        &self,
+        taginfo: &TagInfo,
+        variants: &[&ast::Variant<'a>],
+        cases: &[Case],
+    ) -> UnionFields {
+        let union_fields = self.global_attrs.union_fields.unwrap_or_default();
+        if union_fields == UnionFields::Alias {
+            return union_fields;
+        }
+        if union_fields == UnionFields::Inline && (taginfo.tag.is_some() || taginfo.untagged) {
+            self.err_msg(
+                &self.ident,
+                &format!(
+                    "{}: #[fs(union_fields = \"inline\")] needs an externally tagged enum",
+                    self.ident
+                ),
+            );
+        }
+        for (variant, case) in variants.iter().zip(cases) {
+            if let CaseFields::Struct(ref fields) = case.fields {
+                if fields.iter().any(|f| f.flatten || f.transform.is_some()) {
+                    self.err_msg(
+                        &variant.ident,
+                        &format!(
+                            "{}::{}: #[serde(flatten)] and base64 fields need #[fs(union_fields = \"alias\")]",
+                            self.ident, variant.ident
+                        ),
+                    );
+                }
+            }
+        }
+        union_fields
+    }
+
+    /// `#[fs(handler_name = "...", handler_return = "...")]`, either of which
+    /// asks for a handler: `EnumHandler` returning `unit` by default.
+    fn handler(&self) -> Option<Handler> {
+        let attrs = &self.global_attrs;
+        if attrs.handler_name.is_none() && attrs.handler_return.is_none() {
+            return None;
+        }
+        Some(Handler {
+            name: attrs
+                .handler_name
+                .clone()
+                .unwrap_or_else(|| format!("{}Handler", self.ident)),
+            return_type: attrs
+                .handler_return
+                .clone()
+                .unwrap_or_else(|| "unit".to_string()),
+        })
+    }
+
+    /// Depends on TagInfo for layout
+    /// example variant: `C(u32)`
+    fn derive_newtype_variant(&self, taginfo: &TagInfo, field: &ast::Field<'a>) -> CaseFields {
+        if field.attrs.skip_serializing() {
+            return CaseFields::Unit;
+        };
+        let ty = self.field_to_fs(field);
+        if taginfo.tag.is_some() && taginfo.content.is_none() && !is_taggable(&ty) {
+            // serde fails at runtime to add the tag to these
+            self.err_msg(
+                field.original,
+                &format!(
+                    "{}: the newtype variants of an internally tagged enum must hold a struct or a map",
+                    self.ident
+                ),
+            );
+        }
+        CaseFields::Newtype(ty)
+    }
+
+    /// Depends on TagInfo for layout
+    /// `C { a: u32, b: u32 }` => `C of EnumC` with `type EnumC = { a: uint32; b: uint32 }`
+    fn derive_struct_variant(&self, _taginfo: &TagInfo, fields: &[ast::Field<'a>]) -> CaseFields {
+        let fields = filter_visible(fields);
+        if fields.is_empty() {
+            return CaseFields::Unit;
+        }
+        self.check_unique_labels(&fields);
+
+        CaseFields::Struct(self.derive_fields(&fields))
+    }
+
+    /// `B(u32, u32)` => `B of EnumB` with `type EnumB = uint32 * uint32`
+    fn derive_tuple_variant(&self, _taginfo: &TagInfo, fields: &[ast::Field<'a>]) -> CaseFields {
+        // `B()` is still an (empty) array
+        CaseFields::Tuple(self.derive_field_tuple(&filter_visible(fields)))
+    }
+}
+
+/// can serde write the tag of an internally tagged enum into a newtype of `ty`:
+/// it must serialize as a JSON object
+fn is_taggable(ty: &TypeRef) -> bool {
+    match ty {
+        TypeRef::Primitive(p) => *p == Primitive::Obj,
+        TypeRef::Tuple(_)
+        | TypeRef::List(_)
+        | TypeRef::Array(_)
+        | TypeRef::Option(_)
+        | TypeRef::Set(_)
+        | TypeRef::Unsupported(_) => false,
+        TypeRef::TypeParam(_)
+        | TypeRef::Named { .. }
+        | TypeRef::Map(..)
+        | TypeRef::Result(..)
+        | TypeRef::Struct(_)
+        | TypeRef::Verbatim(_) => true,
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use serde_derive_internals::ast;
+
+use super::{filter_visible, ParseContext};
+use crate::model::{Primitive, TypeBody, TypeRef};
+
+const DEFAULT_ERROR: &str = "struct cannot have a handler or factory";
+
+// types that round trip the JSON serde_json produces for
+// structs with no (visible) fields through FSharp.Json.
+/// `struct S;` serializes as `null`
+fn unit_shape() -> TypeRef {
+    TypeRef::Primitive(Primitive::Unit)
+}
+/// `struct S { #[serde(skip)] a: i32 }` serializes as `{}`
+fn empty_record_shape() -> TypeRef {
+    TypeRef::map(
+        TypeRef::Primitive(Primitive::String),
+        TypeRef::Primitive(Primitive::Unit),
+    )
+}
+/// `struct S(#[serde(skip)] i32, #[serde(skip)] i32)` serializes as `[]`
+pub(crate) fn empty_tuple_shape() -> TypeRef {
+    TypeRef::list(TypeRef::Primitive(Primitive::Unit))
+}
+
+impl<'a> ParseContext<'_> {
+    pub(crate) fn derive_struct(
+        &self,
+        style: ast::Style,
+        fields: &[ast::Field<'a>],
+        container: &ast::Container,
+    ) -> TypeBody {
+        let attrs = &self.global_attrs;
+        if attrs.handler_name.is_some()
+            || attrs.handler_return.is_some()
+            || attrs.factory_name.is_some()
+            || attrs.factory_return_name.is_some()
+        {
+            self.err_msg(&self.ident, &format!("{}: {}", self.ident, DEFAULT_ERROR));
+        }
+        if attrs.union_fields.is_some() {
+            self.err_msg(
+                &self.ident,
+                &format!("{}: union_fields only applies to enums", self.ident),
+            );
+        }
+        match style {
+            ast::Style::Struct => self.derive_struct_named_fields(fields, container),
+            ast::Style::Newtype => self.derive_struct_newtype(&fields[0], container),
+            ast::Style::Tuple => self.derive_struct_tuple(fields, container),
+            ast::Style::Unit => self.derive_struct_unit(),
+        }
+    }
+
+    fn derive_struct_newtype(
+        &self,
+        field: &ast::Field<'a>,
+        ast_container: &ast::Container,
+    ) -> TypeBody {
+        if field.attrs.skip_serializing() {
+            return self.derive_struct_unit();
+        }
+        self.check_flatten(&[field], ast_container);
+
+        TypeBody::Alias(self.field_to_fs(field))
+    }
+
+    fn derive_struct_unit(&self) -> TypeBody {
+        TypeBody::Alias(unit_shape())
+    }
+
+    fn derive_struct_named_fields(
+        &self,
+        fields: &[ast::Field<'a>],
+        ast_container: &ast::Container,
+    ) -> TypeBody {
+        let fields = filter_visible(fields);
+        if fields.is_empty() {
+            return TypeBody::Alias(empty_record_shape());
+        };
+
+        if fields.len() == 1 && ast_container.attrs.transparent() {
+            return self.derive_struct_newtype(fields[0], ast_container);
+        };
+        self.check_unique_labels(&fields);
+
+        TypeBody::Record(self.derive_fields(&fields))
+    }
+
+    fn derive_struct_tuple(
+        &self,
+        fields: &[ast::Field<'a>],
+        ast_container: &ast::Container,
+    ) -> TypeBody {
+        let fields = filter_visible(fields);
+        if fields.is_empty() {
+            return TypeBody::Alias(empty_tuple_shape());
+        }
+
+        if fields.len() == 1 && ast_container.attrs.transparent() {
+            return self.derive_struct_newtype(fields[0], ast_container);
+        };
+        self.check_flatten(&fields, ast_container);
+
+        TypeBody::Alias(TypeRef::Tuple(self.derive_field_tuple(&fields)))
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Where `#[derive(FSharpDefinition)]` puts its sections. The settings are
+//! read from the environment each time the macro is expanded:
+//!
+//! * `FSFY_SHOW_CODE=1`: print the sections to stderr.
+//! * `FSFY_OUT_DIR=dir`: write the sections of each type to `dir/TypeName.fs`.
+//!   Two different types with the same name (in different modules) are an
+//!   error rather than the last one silently winning.
+//! * `FSFY_ONLY=TypeA,TypeB`: only export these types.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// the sections written to each `FSFY_OUT_DIR` file while compiling this crate:
+/// the macro is loaded once per crate so this sees all its derived types.
+static WRITTEN: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExportSettings {
+    /// `FSFY_SHOW_CODE=1`
+    pub show_code: bool,
+    /// `FSFY_OUT_DIR`
+    pub out_dir: Option<PathBuf>,
+    /// `FSFY_ONLY`, comma separated
+    pub only: Option<Vec<String>>,
+}
+
+impl ExportSettings {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        ExportSettings {
+            show_code: var("FSFY_SHOW_CODE").as_deref() == Some("1"),
+            out_dir: var("FSFY_OUT_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            only: var("FSFY_ONLY").map(|only| {
+                only.split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            }),
+        }
+    }
+
+    /// should the sections of `ident` go anywhere?
+    pub fn exports(&self, ident: &syn::Ident) -> bool {
+        (self.show_code || self.out_dir.is_some())
+            && self
+                .only
+                .as_ref()
+                .is_none_or(|only| only.iter().any(|name| ident == name))
+    }
+
+    /// print and/or write the `sections` of `ident`
+    pub fn export(&self, ident: &syn::Ident, sections: &str) -> Result<(), String> {
+        if self.show_code {
+            eprint!("{}", sections);
+        }
+        if let Some(ref dir) = self.out_dir {
+            let path = dir.join(format!("{}.fs", ident));
+            let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+            if written
+                .get(&path)
+                .is_some_and(|previous| previous != sections)
+            {
+                return Err(format!(
+                    "FSFY_OUT_DIR: {}: conflicting definitions of `{}`: another type with this name was already written",
+                    path.display(),
+                    ident
+                ));
+            }
+            written.insert(path.clone(), sections.to_string());
+            fs::create_dir_all(dir)
+                .and_then(|_| fs::write(&path, sections))
+                .map_err(|e| format!("FSFY_OUT_DIR: {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExportSettings;
+    use std::path::PathBuf;
+
+    #[test]
+    fn settings_from_vars() {
+        let settings = ExportSettings::from_vars(|name| match name {
+            "FSFY_OUT_DIR" => Some("fsharp/sections".to_string()),
+            "FSFY_ONLY" => Some("Point, Line,".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            settings,
+            ExportSettings {
+                show_code: false,
+                out_dir: Some(PathBuf::from("fsharp/sections")),
+                only: Some(vec!["Point".to_string(), "Line".to_string()]),
+            }
+        );
+        let ident = |name| syn::Ident::new(name, proc_macro2::Span::call_site());
+        assert!(settings.exports(&ident("Line")));
+        assert!(!settings.exports(&ident("Circle")));
+        assert!(!ExportSettings::from_vars(|_| None).exports(&ident("Line")));
+    }
+
+    #[test]
+    fn conflicting_out_dir_files() {
+        let dir = std::env::temp_dir().join(format!("fsfy-export-{}", std::process::id()));
+        let settings = ExportSettings {
+            out_dir: Some(dir.clone()),
+            ..ExportSettings::default()
+        };
+        let ident = syn::Ident::new("Point", proc_macro2::Span::call_site());
+        let a = settings.export(&ident, "type Point = int32\n");
+        let again = settings.export(&ident, "type Point = int32\n");
+        let b = settings.export(&ident, "type Point = string\n");
+        let written = std::fs::read_to_string(dir.join("Point.fs"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(a, Ok(()));
+        assert_eq!(again, Ok(()));
+        assert!(b
+            .unwrap_err()
+            .contains("conflicting definitions of `Point`"));
+        assert_eq!(written.unwrap(), "type Point = int32\n");
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Turns rust names into FSharp identifiers.
+//!
+//! Every record label, union case and type name the derive emits goes
+//! through here: FSharp keywords (and reserved words) or names that are
+//! not FSharp identifiers are ``` ``quoted`` ```, raw rust identifiers
+//! (`r#type`) lose their prefix and union cases are capitalized.
+
+/// FSharp keywords, OCaml compatibility keywords and words reserved for future use.
+const RESERVED: &[&str] = &[
+    "abstract",
+    "and",
+    "as",
+    "asr",
+    "assert",
+    "base",
+    "begin",
+    "break",
+    "checked",
+    "class",
+    "component",
+    "const",
+    "constraint",
+    "continue",
+    "default",
+    "delegate",
+    "do",
+    "done",
+    "downcast",
+    "downto",
+    "elif",
+    "else",
+    "end",
+    "event",
+    "exception",
+    "extern",
+    "external",
+    "false",
+    "finally",
+    "fixed",
+    "for",
+    "fun",
+    "function",
+    "global",
+    "if",
+    "in",
+    "include",
+    "inherit",
+    "inline",
+    "interface",
+    "internal",
+    "land",
+    "lazy",
+    "let",
+    "lor",
+    "lsl",
+    "lsr",
+    "lxor",
+    "match",
+    "member",
+    "mixin",
+    "mod",
+    "module",
+    "mutable",
+    "namespace",
+    "new",
+    "null",
+    "of",
+    "open",
+    "or",
+    "override",
+    "parallel",
+    "private",
+    "process",
+    "protected",
+    "public",
+    "pure",
+    "rec",
+    "return",
+    "sealed",
+    "sig",
+    "static",
+    "struct",
+    "tailcall",
+    "then",
+    "to",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "upcast",
+    "use",
+    "val",
+    "virtual",
+    "void",
+    "when",
+    "while",
+    "with",
+    "yield",
+];
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+        }
+        _ => false,
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    // backticks can't be escaped inside ``...``
+    format!("``{}``", name.replace('`', "'"))
+}
+
+/// a record label or type name for `name`.
+pub fn fs_ident(name: &str) -> String {
+    let name = name.trim_start_matches("r#");
+    // a lone `_` is a wildcard, not a name
+    if is_ident(name) && name != "_" && !RESERVED.contains(&name) {
+        name.to_string()
+    } else {
+        quote_ident(name)
+    }
+}
+
+/// a union case name for `name`: FSharp requires cases to start with an uppercase letter.
+pub fn fs_case_ident(name: &str) -> String {
+    let name = name.trim_start_matches("r#");
+    let mut chars = name.chars();
+    let case = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => String::new(),
+    };
+    if is_ident(&case) && case.starts_with(char::is_uppercase) {
+        case
+    } else {
+        quote_ident(&case)
+    }
+}
+
+/// the .NET name of a (possibly ``quoted``) identifier, which is what FSharp.Json reads and writes.
+pub fn dotnet_name(ident: &str) -> &str {
+    ident
+        .strip_prefix("``")
+        .and_then(|i| i.strip_suffix("``"))
+        .unwrap_or(ident)
+}
@@ -0,0 +1,225 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A tiny lexer/parser that checks a user supplied `#[fs(fs_type = "...")]`
+//! is a plausible FSharp type expression, e.g. `string`, `int64 list`,
+//! `Map<string, 'T>`, `(int * string) option`, `byte[]` or `{| a: int |}`.
+//!
+//! We don't try to be the FSharp compiler: this only catches typos
+//! (unbalanced brackets, stray punctuation, TypeScript-isms like `a | b`)
+//! at macro expansion time instead of when compiling the generated `.fs` file.
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Ident(String),
+    TyPar(String),
+    Sym(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "{|", "|}", "->", "(", ")", "<", ">", "[", "]", "{", "}", ",", "*", ":", ";", ".",
+];
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+fn lex(v: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = v;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("``") {
+            // ``quoted identifier``
+            match rest[2..].find("``") {
+                Some(end) => {
+                    tokens.push(Token::Ident(rest[..end + 4].to_string()));
+                    rest = &rest[end + 4..];
+                }
+                None => return Err("unterminated ``identifier``".to_string()),
+            }
+        } else if c == '\'' || c == '^' {
+            // generic type parameter 'T or statically resolved ^T
+            let end = rest[1..]
+                .find(|c: char| !is_ident_char(c))
+                .map_or(rest.len(), |n| n + 1);
+            let name = &rest[1..end];
+            if !name.starts_with(is_ident_start) {
+                return Err(format!("bad type parameter `{}`", &rest[..end]));
+            }
+            tokens.push(Token::TyPar(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if is_ident_start(c) {
+            let end = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if let Some(sym) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+            tokens.push(Token::Sym(sym));
+            rest = &rest[sym.len()..];
+        } else {
+            return Err(format!("unexpected character `{}`", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+type PResult = Result<(), String>;
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_sym(&self, sym: &str) -> bool {
+        matches!(self.peek(), Some(Token::Sym(s)) if *s == sym)
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        if self.peek_sym(sym) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> PResult {
+        if self.eat_sym(sym) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}` {}", sym, self.found()))
+        }
+    }
+
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(s)) | Some(Token::TyPar(s)) => format!("found `{}`", s),
+            Some(Token::Sym(s)) => format!("found `{}`", s),
+            None => "found end of type".to_string(),
+        }
+    }
+
+    fn ident(&mut self) -> PResult {
+        match self.peek() {
+            Some(Token::Ident(..)) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(format!("expected identifier {}", self.found())),
+        }
+    }
+
+    // type := tuple ( "->" type )?
+    fn ty(&mut self) -> PResult {
+        self.tuple()?;
+        if self.eat_sym("->") {
+            self.ty()?;
+        }
+        Ok(())
+    }
+
+    // tuple := postfix ( "*" postfix )*
+    fn tuple(&mut self) -> PResult {
+        self.postfix()?;
+        while self.eat_sym("*") {
+            self.postfix()?;
+        }
+        Ok(())
+    }
+
+    // postfix := atom ( longident | "[" ","* "]" )*   e.g. `int list option`, `byte[]`
+    fn postfix(&mut self) -> PResult {
+        self.atom()?;
+        loop {
+            match self.peek() {
+                Some(Token::Ident(..)) => self.long_ident()?,
+                Some(Token::Sym("[")) => {
+                    self.pos += 1;
+                    while self.eat_sym(",") {}
+                    self.expect_sym("]")?;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn long_ident(&mut self) -> PResult {
+        self.ident()?;
+        while self.eat_sym(".") {
+            self.ident()?;
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self) -> PResult {
+        match self.peek() {
+            Some(Token::TyPar(..)) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(Token::Ident(..)) => {
+                self.long_ident()?;
+                if self.eat_sym("<") {
+                    self.ty()?;
+                    while self.eat_sym(",") {
+                        self.ty()?;
+                    }
+                    self.expect_sym(">")?;
+                }
+                Ok(())
+            }
+            Some(Token::Sym("(")) => {
+                self.pos += 1;
+                self.ty()?;
+                self.expect_sym(")")
+            }
+            Some(Token::Sym("{|")) => {
+                self.pos += 1;
+                self.fields("|}")
+            }
+            _ => Err(format!("expected a type {}", self.found())),
+        }
+    }
+
+    // fields := ( ident ":" type ( ";" ident ":" type )* ";"? )? close
+    fn fields(&mut self, close: &str) -> PResult {
+        while !self.eat_sym(close) {
+            self.ident()?;
+            self.expect_sym(":")?;
+            self.ty()?;
+            if !self.eat_sym(";") {
+                return self.expect_sym(close);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// check that `v` lexes and parses as a FSharp type expression.
+pub fn fs_type_check(v: &str) -> Result<(), String> {
+    let tokens = lex(v)?;
+    if tokens.is_empty() {
+        return Err("empty type".to_string());
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    parser.ty()?;
+    match parser.peek() {
+        None => Ok(()),
+        Some(..) => Err(format!("unexpected trailing input: {}", parser.found())),
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// Supporting FSharp definitions that generated types can refer to.
+///
+/// These are shared between types so they are exported as separate
+/// `(* ♒︎ helper(...) *)` sections that the organizer de-duplicates
+/// and places before all the type sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Helper {
+    /// FSharp.Json transform for `byte[]` fields serialized as base64 strings
+    Base64Bytes,
+}
+
+impl Helper {
+    pub fn name(self) -> &'static str {
+        match self {
+            Helper::Base64Bytes => "Base64Bytes",
+        }
+    }
+
+    pub fn source(self) -> &'static str {
+        match self {
+            Helper::Base64Bytes => {
+                "/// FSharp.Json transform for byte buffers serialized as base64 strings
+type Base64Bytes() =
+  interface ITypeTransform with
+    member x.targetType () = typeof<string>
+    member x.toTargetType value = System.Convert.ToBase64String(value :?> byte[]) :> obj
+    member x.fromTargetType value = System.Convert.FromBase64String(value :?> string) :> obj"
+            }
+        }
+    }
+}
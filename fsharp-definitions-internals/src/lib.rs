@@ -0,0 +1,723 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The machinery behind the `fsharp-definitions` derive macros and
+//! `fsharp_definitions::build::Generator`: turns a serde-serializable
+//! struct or enum into FSharp definitions.
+//!
+//! Please see documentation at [crates.io](https://crates.io/crates/fsharp-definitions)
+#![allow(dead_code)]
+use quote::{quote, quote_spanned};
+use serde_derive_internals::{ast, attr, Ctxt, Derive};
+use syn::spanned::Spanned;
+use syn::DeriveInput;
+
+use source_builder::SourceBuilder;
+
+mod attrs;
+mod derive_enum;
+mod derive_struct;
+mod export;
+mod fs_ident;
+mod fs_type;
+mod helpers;
+pub mod model;
+mod render;
+mod source_builder;
+mod tests;
+mod tots;
+mod utils;
+
+use attrs::{Attrs, LabelCase};
+pub use export::ExportSettings;
+use fs_ident::fs_ident;
+use helpers::Helper;
+use model::{Field, TypeBody, TypeDefinition, TypeRef};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use utils::*;
+
+// too many TokenStreams around! give it a different name
+type RustQuote = proc_macro2::TokenStream;
+
+/* #region helpers */
+
+/// The [`TypeDefinition`](model::TypeDefinition) of a serde-serializable struct or enum.
+pub fn type_definition(input: DeriveInput) -> Result<TypeDefinition, Vec<syn::Error>> {
+    Ok(FSharpify::from_derive_input(input)?.parse()?.definition)
+}
+
+/// The sections `#[derive(FSharpDefinition)]` exports for a type: any
+/// `(* ♒︎ helper(...) *)` sections it needs, its `(* ♒︎ section(...) *)`
+/// and, for records, a `(* ♒︎ fields(...) *)` section for types that flatten it.
+/// See `fsharp_definitions::organize` for how they are put together.
+pub fn definition_sections(input: DeriveInput) -> Result<String, Vec<syn::Error>> {
+    let tsy = FSharpify::from_derive_input(input)?;
+    Ok(tsy.parse()?.sections(&tsy.ident))
+}
+
+/// What `#[derive(FSharpDefinition)]` generates: the hidden
+/// `HasFSharpDefinition` impl and checks that the types it refers to have a
+/// definition too. The sections are only written if the type is exported.
+pub fn derive_fsharp_definition(input: DeriveInput, settings: &ExportSettings) -> RustQuote {
+    let warnings = deprecation_warnings(&input);
+    let tsy = match FSharpify::from_derive_input(input) {
+        Ok(tsy) => tsy,
+        Err(errors) => return to_compile_errors(errors),
+    };
+    let marker = tsy.has_fsharp_definition();
+    let parsed = match tsy.parse() {
+        Ok(parsed) => parsed,
+        Err(errors) => return to_compile_errors(errors),
+    };
+    if settings.exports(&tsy.ident) {
+        if let Err(e) = settings.export(&tsy.ident, &parsed.sections(&tsy.ident)) {
+            return to_compile_errors(vec![syn::Error::new(tsy.ident.span(), e)]);
+        }
+    }
+    let checks = tsy.check_references(&parsed);
+    quote!(#warnings #marker #checks)
+}
+
+/// The `impl FSharpifyTrait` generated by `#[derive(FSharpify)]`.
+pub fn derive_fsharpify(input: RustQuote) -> RustQuote {
+    let tsy = match syn::parse2(input)
+        .map_err(|e| vec![e])
+        .and_then(FSharpify::from_derive_input)
+    {
+        Ok(tsy) => tsy,
+        Err(errors) => return to_compile_errors(errors),
+    };
+    let warnings = deprecation_warnings(&tsy.input);
+    let parsed = match tsy.parse() {
+        Ok(parsed) => parsed,
+        Err(errors) => return to_compile_errors(errors),
+    };
+    let export_string = parsed.export_type_definition_source().finish();
+
+    let ident = &tsy.ident;
+    let (impl_generics, ty_generics, where_clause) = tsy.generics.split_for_impl();
+
+    // replace `(* ♒︎ flatten(X) *)` markers with the fields of `X` at runtime
+    let flattened = &parsed.flattened;
+    let resolve = |source: String| {
+        if flattened.is_empty() {
+            quote!(::std::borrow::Cow::Borrowed(#source))
+        } else {
+            let names = flattened.iter().map(|(name, _)| name);
+            let types = flattened.iter().map(|(_, ty)| ty);
+            quote!(::std::borrow::Cow::Owned(::fsharp_definitions::flatten_fields(
+                #source,
+                &[#((#names, <#types as ::fsharp_definitions::FSharpifyTrait>::fsharp_fields())),*],
+            )))
+        }
+    };
+    let fsharp_ify = resolve(export_string);
+    let fsharp_fields = parsed.export_fields_source().map(|fields| {
+        let fields = resolve(fields);
+        quote! {
+            fn fsharp_fields() -> ::std::option::Option<::std::borrow::Cow<'static, str>> {
+                ::std::option::Option::Some(#fields)
+            }
+        }
+    });
+
+    let references = &parsed.definition.references;
+    let fsharp_references = if references.is_empty() {
+        None
+    } else {
+        let names = references.iter();
+        Some(quote! {
+            fn fsharp_references() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+        })
+    };
+
+    let checks = tsy.check_references(&parsed);
+
+    quote! {
+        #warnings
+        #checks
+        impl #impl_generics ::fsharp_definitions::FSharpifyTrait for #ident #ty_generics #where_clause {
+            fn fsharp_ify() -> ::std::borrow::Cow<'static, str> {
+                #fsharp_ify
+            }
+            #fsharp_fields
+            #fsharp_references
+        }
+    }
+}
+
+/// placeholder for the fields of a `#[serde(flatten)]` struct
+fn flatten_marker(name: &str) -> String {
+    format!("(* ♒︎ flatten({}) *)", name)
+}
+
+/// the types a section refers to
+fn references_marker(names: &[String]) -> String {
+    format!("(* ♒︎ references({}) *)", names.join(", "))
+}
+
+/// A warning for each deprecated `#[fs(ts_as = "...")]`: the use of a
+/// `#[deprecated]` item spanned to the attribute.
+pub fn deprecation_warnings(input: &DeriveInput) -> RustQuote {
+    let warnings = attrs::deprecated_ts_as(input).into_iter().map(|span| {
+        quote_spanned! {span=>
+            const _: () = {
+                #[deprecated(note = "#[fs(ts_as = \"...\")] is deprecated, use #[fs(fs_as = \"...\")]")]
+                #[allow(non_camel_case_types)]
+                struct ts_as;
+                let _ = ts_as;
+            };
+        }
+    });
+    quote!(#(#warnings)*)
+}
+
+pub fn to_compile_errors(errors: Vec<syn::Error>) -> RustQuote {
+    let compile_errors = errors.iter().map(syn::Error::to_compile_error);
+    quote!(#(#compile_errors)*)
+}
+
+/* #endregion helpers */
+
+pub(crate) struct FSharpify {
+    ident: syn::Ident,
+    generics: syn::Generics,
+    input: DeriveInput,
+}
+
+impl FSharpify {
+    pub fn new(input: RustQuote) -> Self {
+        Self::from_derive_input(syn::parse2(input).unwrap()).unwrap()
+    }
+
+    pub fn from_derive_input(input: DeriveInput) -> Result<Self, Vec<syn::Error>> {
+        // #[fs(...)] attributes are checked (and errors reported) in `parse`
+        let cx = Ctxt::new();
+        let container = container_from_ast(&cx, &input);
+        // always consume the errors: serde panics about unchecked errors otherwise
+        cx.check()?;
+        let container = container?;
+
+        Ok(Self {
+            generics: container.generics.clone(),
+            ident: container.ident,
+            input,
+        })
+    }
+
+    /// `impl HasFSharpDefinition for Type {}`
+    fn has_fsharp_definition(&self) -> RustQuote {
+        let ident = &self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        quote! {
+            impl #impl_generics ::fsharp_definitions::HasFSharpDefinition for #ident #ty_generics #where_clause {}
+        }
+    }
+
+    /// A compile time check that the (non builtin) types the definition refers
+    /// to derive `FSharpDefinition` or `FSharpify` too, so the FSharp doesn't
+    /// refer to a missing type (see `fsharp_definitions::check`). The errors
+    /// point at the field.
+    fn check_references(&self, parsed: &FSOutput) -> RustQuote {
+        let mut seen = BTreeSet::new();
+        let checks = parsed
+            .referenced_types
+            .iter()
+            .filter(|ty| seen.insert(quote!(#ty).to_string()))
+            .map(|ty| {
+                quote_spanned! {ty.span()=>
+                    (&&&Probe::<#ty>::new()).has_fsharp_definition();
+                }
+            })
+            .collect::<Vec<_>>();
+        if checks.is_empty() {
+            return quote!();
+        }
+        let (impl_generics, _, where_clause) = self.generics.split_for_impl();
+        quote! {
+            const _: () = {
+                #[allow(dead_code)]
+                fn references #impl_generics () #where_clause {
+                    use ::fsharp_definitions::check::*;
+                    #(#checks)*
+                }
+            };
+        }
+    }
+
+    fn parse(&self) -> Result<FSOutput, Vec<syn::Error>> {
+        let input = &self.input;
+        let cx = Ctxt::new();
+
+        // collect and check #[fs(...attrs)]
+        let attrs = {
+            let mut attrs = attrs::Attrs::new();
+            attrs.push_doc_comment(&input.attrs);
+            attrs.push_attrs(&input.ident, &input.attrs, &cx);
+            attrs
+        };
+
+        let container = match container_from_ast(&cx, input) {
+            Ok(container) => container,
+            Err(errors) => {
+                cx.check()?;
+                return Err(errors);
+            }
+        };
+
+        let pctxt = ParseContext {
+            ctxt: &cx,
+            global_attrs: attrs,
+            ident: container.ident.clone(),
+            type_params: container
+                .generics
+                .type_params()
+                .map(|p| p.ident.clone())
+                .collect(),
+            helpers: RefCell::new(BTreeSet::new()),
+            container_default: !matches!(container.attrs.default(), attr::Default::None),
+            flattened: RefCell::new(vec![]),
+            references: RefCell::new(BTreeSet::new()),
+            referenced_types: RefCell::new(vec![]),
+        };
+
+        let body = match (&pctxt.global_attrs.fs_as, &container.data) {
+            (Some(ty), _) => pctxt.derive_as(ty),
+            (None, ast::Data::Enum(ref variants)) => pctxt.derive_enum(variants, &container),
+            (None, ast::Data::Struct(style, ref fields)) => {
+                pctxt.derive_struct(*style, fields, &container)
+            }
+        };
+        if let TypeBody::Alias(ref ty) = body {
+            pctxt.check_alias_type_params(ty);
+        }
+
+        let definition = TypeDefinition {
+            name: container.ident.to_string(),
+            type_params: pctxt.type_params.iter().map(|p| p.to_string()).collect(),
+            doc: pctxt.global_attrs.doc.clone(),
+            body,
+            references: pctxt.references.into_inner().into_iter().collect(),
+        };
+        let output = FSOutput {
+            definition,
+            helpers: pctxt.helpers.into_inner(),
+            flattened: pctxt.flattened.into_inner(),
+            referenced_types: pctxt.referenced_types.into_inner(),
+        };
+        cx.check()?;
+        Ok(output)
+    }
+}
+
+/// serde's view of the type, an error (besides the ones in `cx`) if it can't be serialized
+fn container_from_ast<'a>(
+    cx: &Ctxt,
+    input: &'a DeriveInput,
+) -> Result<ast::Container<'a>, Vec<syn::Error>> {
+    ast::Container::from_ast(cx, input, Derive::Serialize).ok_or_else(|| {
+        vec![syn::Error::new_spanned(
+            &input.ident,
+            format!("{}: serde can't serialize this type", input.ident),
+        )]
+    })
+}
+
+struct FSOutput {
+    definition: TypeDefinition,
+    /// helper definitions the generated type refers to
+    helpers: BTreeSet<Helper>,
+    /// `#[serde(flatten)]` structs by flatten marker name
+    flattened: Vec<(String, syn::Type)>,
+    /// the rust types behind `definition.references`, as written
+    referenced_types: Vec<syn::Type>,
+}
+
+impl FSOutput {
+    /// see [`definition_sections`]
+    fn sections(&self, ident: &syn::Ident) -> String {
+        let export_string = self.export_type_definition_source().finish();
+        let ident_string = ident.to_string();
+
+        let mut sections = String::new();
+        for helper in &self.helpers {
+            sections.push_str(&format!(
+                "\n(* ♒︎ helper({}) *)\n{}\n(* ♒︎ helper end({}) *)\n",
+                helper.name(),
+                helper.source(),
+                helper.name(),
+            ));
+        }
+        // the organizer groups and orders the sections by what they refer to
+        let references = &self.definition.references;
+        let references = if references.is_empty() {
+            String::new()
+        } else {
+            format!("\n{}", references_marker(references))
+        };
+        sections.push_str(&format!(
+            "\n(* ♒︎ section({}) *){}\n{}\n(* ♒︎ section end({}) *)\n",
+            &ident_string, &references, &export_string, &ident_string,
+        ));
+        // the organizer replaces `(* ♒︎ flatten(X) *)` markers with these
+        if let Some(fields) = self.export_fields_source() {
+            sections.push_str(&format!(
+                "\n(* ♒︎ fields({}) *)\n{}\n(* ♒︎ fields end({}) *)\n",
+                &ident_string, &fields, &ident_string,
+            ));
+        }
+        sections
+    }
+
+    /// the record fields, one per line without indentation
+    fn export_fields_source(&self) -> Option<String> {
+        render::fields_source(&self.definition)
+    }
+
+    fn export_type_definition_source(&self) -> SourceBuilder {
+        render::definition_source(&self.definition)
+    }
+}
+
+fn return_type(rt: &syn::ReturnType) -> Option<syn::Type> {
+    match rt {
+        syn::ReturnType::Default => None, // e.g. undefined
+        syn::ReturnType::Type(_, tp) => Some(*tp.clone()),
+    }
+}
+
+// represents a fsharp type T<A,B>
+struct FSType {
+    ident: syn::Ident,
+    args: Vec<syn::Type>,
+    path: Vec<syn::Ident>,          // full path
+    return_type: Option<syn::Type>, // only if function
+}
+
+impl FSType {
+    fn path(&self) -> Vec<String> {
+        self.path.iter().map(|i| i.to_string()).collect() // hold the memory
+    }
+}
+
+fn last_path_element(path: &syn::Path) -> Option<FSType> {
+    let fullpath = path
+        .segments
+        .iter()
+        .map(|s| s.ident.clone())
+        .collect::<Vec<_>>();
+    match path.segments.last() {
+        Some(t) => {
+            let ident = t.ident.clone();
+            let args = match &t.arguments {
+                syn::PathArguments::AngleBracketed(ref path) => &path.args,
+                // closures Fn(A,B) -> C
+                syn::PathArguments::Parenthesized(ref path) => {
+                    let args: Vec<_> = path.inputs.iter().cloned().collect();
+                    let ret = return_type(&path.output);
+                    return Some(FSType {
+                        ident,
+                        args,
+                        path: fullpath,
+                        return_type: ret,
+                    });
+                }
+                syn::PathArguments::None => {
+                    return Some(FSType {
+                        ident,
+                        args: vec![],
+                        path: fullpath,
+                        return_type: None,
+                    });
+                }
+            };
+            // ignore lifetimes
+            let args = args
+                .iter()
+                .filter_map(|p| match p {
+                    syn::GenericArgument::Type(t) => Some(t),
+                    syn::GenericArgument::Binding(t) => Some(&t.ty),
+                    syn::GenericArgument::Constraint(..) => None,
+                    syn::GenericArgument::Const(..) => None,
+                    _ => None, // lifetimes, expr, constraints A : B ... skip!
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            Some(FSType {
+                ident,
+                path: fullpath,
+                args,
+                return_type: None,
+            })
+        }
+        None => None,
+    }
+}
+
+pub(crate) struct FieldContext<'a> {
+    pub ctxt: &'a ParseContext<'a>,        // global parse context
+    pub field: Option<&'a ast::Field<'a>>, // field being parsed, `None` for a container `fs_as`
+    pub attrs: Attrs,                      // field attributes
+}
+
+impl<'a> FieldContext<'a> {
+    pub fn get_path(&self, ty: &syn::Type) -> Option<FSType> {
+        use syn::Type::Path;
+        use syn::TypePath;
+        match ty {
+            Path(TypePath { path, .. }) => last_path_element(path),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct ParseContext<'c> {
+    ctxt: &'c Ctxt,                     // serde parse context for error reporting
+    global_attrs: Attrs,                // global #[fs(...)] attributes
+    ident: syn::Ident,                  // name of enum struct
+    type_params: Vec<syn::Ident>,       // generic type parameters (lifetimes and consts dropped)
+    helpers: RefCell<BTreeSet<Helper>>, // helper definitions the generated type refers to
+    container_default: bool,            // #[serde(default)] on the struct
+    flattened: RefCell<Vec<(String, syn::Type)>>, // #[serde(flatten)] structs by flatten marker name
+    references: RefCell<BTreeSet<String>>, // names of the (non builtin) types this type refers to
+    referenced_types: RefCell<Vec<syn::Type>>, // the rust types behind `references`, as written
+}
+
+impl<'a> ParseContext<'_> {
+    // Some helpers
+
+    fn err_msg<A: quote::ToTokens>(&self, tokens: A, msg: &str) {
+        self.ctxt.error_spanned_by(tokens, msg);
+    }
+
+    /// returns { #ty } of
+    fn field_to_fs(&self, field: &ast::Field<'a>) -> TypeRef {
+        let attrs = Attrs::from_field(field, self.ctxt);
+        // if user has provided a type ... use that
+        if let Some(ref s) = attrs.fs_type {
+            // already checked by `Attrs::push_field_attrs`
+            return TypeRef::Verbatim(s.clone());
+        }
+
+        let fc = FieldContext {
+            attrs,
+            ctxt: self,
+            field: Some(field),
+        };
+
+        if let Some(ref ty) = fc.attrs.fs_as {
+            fc.type_to_fs(ty)
+        } else {
+            fc.type_to_fs(field.ty)
+        }
+    }
+
+    /// `#[fs(fs_as = "String")]` on a struct or enum: the type serializes like
+    /// `String` (e.g. it has its own `Serialize` impl) so it is an alias of it.
+    fn derive_as(&self, ty: &syn::Type) -> TypeBody {
+        let attrs = &self.global_attrs;
+        if attrs.handler_name.is_some()
+            || attrs.handler_return.is_some()
+            || attrs.factory_name.is_some()
+            || attrs.factory_return_name.is_some()
+            || attrs.union_fields.is_some()
+        {
+            self.err_msg(
+                &self.ident,
+                &format!(
+                    "{}: fs_as can't be used with handler, factory or union_fields options",
+                    self.ident
+                ),
+            );
+        }
+        let fc = FieldContext {
+            attrs: Attrs::new(),
+            ctxt: self,
+            field: None,
+        };
+        TypeBody::Alias(fc.type_to_fs(ty))
+    }
+
+    /// returns `#field_name: #ty`
+    fn derive_field(&self, field: &ast::Field<'a>) -> Field {
+        if field.attrs.flatten() {
+            return self.derive_flatten_field(field);
+        }
+        let mut ty = self.field_to_fs(field);
+        let mut transform = None;
+        if bytes_kind(field) == Some(BytesKind::Base64)
+            && ty == TypeRef::Primitive(model::Primitive::String)
+        {
+            // decode the base64 string straight into a byte buffer
+            transform = Some(Helper::Base64Bytes.name().to_string());
+            self.helpers.borrow_mut().insert(Helper::Base64Bytes);
+            ty = TypeRef::Array(Box::new(TypeRef::Primitive(model::Primitive::Byte)));
+        }
+        if self.is_optional(field) && !matches!(ty, TypeRef::Option(_)) {
+            ty = TypeRef::option(ty);
+        }
+        Field {
+            name: self.field_name(field),
+            serde_name: field.attrs.name().serialize_name(),
+            ty,
+            // only the doc comments: `field_to_fs` has already reported any #[fs(...)] errors
+            doc: Attrs::from_field_doc(field).doc,
+            flatten: false,
+            transform,
+        }
+    }
+
+    /// `#[serde(flatten)] meta: Meta` is replaced by the fields of `Meta`
+    /// once all the definitions are known.
+    /// A flattened map collects all the other keys.
+    fn derive_flatten_field(&self, field: &ast::Field<'a>) -> Field {
+        let fc = FieldContext {
+            attrs: Attrs::from_field(field, self.ctxt),
+            ctxt: self,
+            field: Some(field),
+        };
+        let ty = match fc.get_path(field.ty) {
+            Some(ref fs) if fs.ident == "HashMap" || fs.ident == "BTreeMap" => {
+                fc.type_to_fs(field.ty)
+            }
+            Some(ref fs) if fs.args.is_empty() && !self.type_params.contains(&fs.ident) => {
+                let name = fs.ident.to_string();
+                let mut flattened = self.flattened.borrow_mut();
+                if !flattened.iter().any(|(n, _)| *n == name) {
+                    flattened.push((name.clone(), field.ty.clone()));
+                }
+                // its fields end up in this definition: the organizer orders by these
+                self.references.borrow_mut().insert(name.clone());
+                self.referenced_types.borrow_mut().push(field.ty.clone());
+                TypeRef::Named { name, args: vec![] }
+            }
+            _ => {
+                self.err_msg(
+                    field.original,
+                    &format!(
+                        "{}: #[serde(flatten)] only works for maps and structs without generics.",
+                        self.ident
+                    ),
+                );
+                TypeRef::Unsupported("flatten".to_string())
+            }
+        };
+        Field {
+            name: self.field_name(field),
+            serde_name: field.attrs.name().serialize_name(),
+            ty,
+            doc: Attrs::from_field_doc(field).doc,
+            flatten: true,
+            transform: None,
+        }
+    }
+
+    /// fields that can be missing from the JSON: serde skips them when
+    /// serializing (`skip_serializing_if`) or fills them in when deserializing
+    /// (`#[serde(default)]` on the field or the struct).
+    fn is_optional(&self, field: &ast::Field<'a>) -> bool {
+        // serde gives `skip_deserializing` fields a default too, but
+        // those are always written so don't make them optional
+        let has_default = !field.attrs.skip_deserializing()
+            && (self.container_default || !matches!(field.attrs.default(), attr::Default::None));
+        field.attrs.skip_serializing_if().is_some() || has_default
+    }
+
+    /// the name of a named field: the rust identifier in the
+    /// `#[fs(rename_all = "...")]` casing (`snake_case`, unchanged, by default).
+    fn field_name(&self, field: &ast::Field<'a>) -> String {
+        let ident = match field.member {
+            syn::Member::Named(ref ident) => ident.to_string(),
+            syn::Member::Unnamed(ref index) => format!("Item{}", index.index + 1),
+        };
+        self.global_attrs
+            .rename_all
+            .unwrap_or(LabelCase::Snake)
+            .apply(&ident)
+    }
+
+    /// record label for a named field
+    fn field_label(&self, field: &ast::Field<'a>) -> String {
+        fs_ident(&self.field_name(field))
+    }
+
+    /// FSharp rejects a type abbreviation (`type Id<'T> = uint64`) that doesn't
+    /// use all its type parameters e.g. `struct Id<T>(u64, PhantomData<T>)`.
+    fn check_alias_type_params(&self, ty: &TypeRef) {
+        let unused = self
+            .type_params
+            .iter()
+            .map(|p| p.to_string())
+            .filter(|p| !ty.uses_type_param(p))
+            .map(|p| format!("'{}", p))
+            .collect::<Vec<_>>();
+        if !unused.is_empty() {
+            self.err_msg(
+                &self.ident,
+                &format!(
+                    "{}: the FSharp type abbreviation doesn't use the type parameter(s) {}, which FSharp rejects; \
+                     remove them or make this a struct with named fields",
+                    self.ident,
+                    unused.join(", ")
+                ),
+            );
+        }
+    }
+
+    /// report names that end up as the same fsharp identifier
+    /// e.g. `content_type` and `contentType` with `#[fs(rename_all = "camelCase")]`.
+    fn check_unique_names<T: quote::ToTokens>(&self, what: &str, names: &[(String, T)]) {
+        let mut seen = BTreeSet::new();
+        for (name, tokens) in names {
+            if !seen.insert(name) {
+                self.err_msg(
+                    tokens,
+                    &format!(
+                        "{} `{}` is used more than once in `{}`",
+                        what, name, self.ident
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_unique_labels(&self, fields: &[&ast::Field<'a>]) {
+        let labels = fields
+            .iter()
+            .filter(|f| !f.attrs.flatten())
+            .map(|f| (self.field_label(f), &f.member))
+            .collect::<Vec<_>>();
+        self.check_unique_names("fsharp record label", &labels);
+    }
+
+    fn derive_fields(&self, fields: &[&ast::Field<'a>]) -> Vec<Field> {
+        fields.iter().map(|f| self.derive_field(f)).collect()
+    }
+
+    fn derive_field_tuple(&self, fields: &[&ast::Field<'a>]) -> Vec<TypeRef> {
+        fields.iter().map(|f| self.field_to_fs(f)).collect()
+    }
+
+    fn check_flatten(&self, fields: &[&'a ast::Field<'a>], ast_container: &ast::Container) -> bool {
+        let has_flatten = fields.iter().any(|f| f.attrs.flatten()); // .any(|f| f);
+        if has_flatten {
+            self.err_msg(
+                &self.ident,
+                &format!(
+                    "{}: #[serde(flatten)] does not work for fsharp-definitions.",
+                    ast_container.ident
+                ),
+            );
+        };
+        has_flatten
+    }
+}
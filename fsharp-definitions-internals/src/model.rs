@@ -0,0 +1,253 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The type model a serde-serializable struct or enum is parsed into.
+//!
+//! The FSharp definitions are rendered from a [`TypeDefinition`] but other
+//! tools (documentation, schemas, diffs) can use it directly: it serializes
+//! with serde. Names are the rust (or serde) names; the FSharp renderer
+//! quotes any that aren't valid FSharp identifiers.
+
+use serde::{Deserialize, Serialize};
+
+/// A struct or enum and everything needed to write its definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeDefinition {
+    /// the rust type name
+    pub name: String,
+    /// generic type parameters, lifetimes and consts dropped
+    pub type_params: Vec<String>,
+    /// doc comment lines
+    pub doc: Vec<String>,
+    pub body: TypeBody,
+    /// the (non builtin) types the definition refers to, sorted
+    pub references: Vec<String>,
+}
+
+/// What a type is made of.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "def", rename_all = "snake_case")]
+pub enum TypeBody {
+    /// newtypes, `#[serde(transparent)]` structs, tuple structs and structs without fields
+    Alias(TypeRef),
+    /// a struct with named fields
+    Record(Vec<Field>),
+    /// an enum
+    Union(Union),
+}
+
+/// A named field of a struct or struct variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    /// the record label: the rust name in the `#[fs(rename_all = "...")]` casing
+    pub name: String,
+    /// the name serde reads and writes
+    pub serde_name: String,
+    /// already a [`TypeRef::Option`] if the field can be missing from the JSON
+    pub ty: TypeRef,
+    /// doc comment lines
+    pub doc: Vec<String>,
+    /// `#[serde(flatten)]`: a struct (a [`TypeRef::Named`]) whose fields are
+    /// merged into this record or a map collecting all the other keys.
+    pub flatten: bool,
+    /// a FSharp.Json `ITypeTransform` that converts the JSON value to `ty`
+    pub transform: Option<String>,
+}
+
+/// An enum: how serde tags it and its variants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Union {
+    pub tagging: Tagging,
+    pub cases: Vec<Case>,
+    /// `#[fs(handler_name = "...")]`
+    pub handler: Option<Handler>,
+    /// `#[fs(factory_name = "...")]`
+    pub factory: Option<Factory>,
+    /// `#[fs(union_fields = "...")]`
+    pub union_fields: UnionFields,
+}
+
+impl Union {
+    /// all variants are unit variants (a C-like enum)
+    pub fn is_enum(&self) -> bool {
+        self.cases
+            .iter()
+            .all(|case| case.fields == CaseFields::Unit)
+    }
+}
+
+/// How the content of a variant appears in its union case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnionFields {
+    /// `| V of EnumV` with a `type EnumV = ...` for each variant (the default)
+    #[default]
+    Alias,
+    /// `| V of foo: bool * bar: int64`
+    Inline,
+    /// `| V of {| foo: bool; bar: int64 |}`
+    Anonymous,
+}
+
+/// A function for each variant, e.g. to handle messages, and a `dispatch`
+/// that calls the one for a value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handler {
+    /// the name of the (record) type of functions
+    pub name: String,
+    /// the (FSharp) type the functions return
+    pub return_type: String,
+}
+
+/// A function for each variant that constructs it from its fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Factory {
+    /// the name of the module of functions
+    pub name: String,
+    /// the (FSharp) type the functions return, the union if `None`
+    pub return_type: Option<String>,
+}
+
+/// <https://serde.rs/enum-representations.html>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum Tagging {
+    /// serde's default: `{ "Variant": ... }` and `"Variant"` for unit variants
+    External,
+    /// `#[serde(tag = "...")]`: variant fields live next to the tag
+    Internal { tag: String },
+    /// `#[serde(tag = "...", content = "...")]`
+    Adjacent { tag: String, content: String },
+    /// `#[serde(untagged)]`: just the variant's content
+    Untagged,
+}
+
+/// An enum variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Case {
+    /// the rust variant name
+    pub name: String,
+    /// the name serde reads and writes
+    pub serde_name: String,
+    /// doc comment lines
+    pub doc: Vec<String>,
+    pub fields: CaseFields,
+}
+
+/// The content of a variant. Skipped fields are left out so e.g. a newtype
+/// variant with a skipped field is a unit variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "style", content = "fields", rename_all = "snake_case")]
+pub enum CaseFields {
+    Unit,
+    Newtype(TypeRef),
+    Tuple(Vec<TypeRef>),
+    Struct(Vec<Field>),
+}
+
+impl CaseFields {
+    /// does the content of the variant refer to the type parameter `name`
+    pub fn uses_type_param(&self, name: &str) -> bool {
+        match self {
+            CaseFields::Unit => false,
+            CaseFields::Newtype(ty) => ty.uses_type_param(name),
+            CaseFields::Tuple(elems) => elems.iter().any(|ty| ty.uses_type_param(name)),
+            CaseFields::Struct(fields) => fields.iter().any(|f| f.ty.uses_type_param(name)),
+        }
+    }
+}
+
+/// A reference to a type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "of", rename_all = "snake_case")]
+pub enum TypeRef {
+    Primitive(Primitive),
+    /// a type parameter of the definition e.g. `T`
+    TypeParam(String),
+    /// another (derived) type, by its rust name
+    Named {
+        name: String,
+        args: Vec<TypeRef>,
+    },
+    Tuple(Vec<TypeRef>),
+    /// `Vec<T>`, `[T]` etc.
+    List(Box<TypeRef>),
+    /// `serde_bytes` and friends: a `byte[]`
+    Array(Box<TypeRef>),
+    Option(Box<TypeRef>),
+    Map(Box<TypeRef>, Box<TypeRef>),
+    Set(Box<TypeRef>),
+    Result(Box<TypeRef>, Box<TypeRef>),
+    /// a struct serialized by serde itself e.g. `Duration` => `{ secs, nanos }`
+    Struct(Vec<(String, TypeRef)>),
+    /// `#[fs(fs_type = "...")]`: written out as is
+    Verbatim(String),
+    /// a rust type with no JSON representation
+    Unsupported(String),
+}
+
+impl TypeRef {
+    /// does the type refer to the type parameter `name` (`'name` in a `fs_type`)
+    pub fn uses_type_param(&self, name: &str) -> bool {
+        let any = |types: &[TypeRef]| types.iter().any(|ty| ty.uses_type_param(name));
+        match self {
+            TypeRef::TypeParam(param) => param == name,
+            TypeRef::Named { args, .. } => any(args),
+            TypeRef::Tuple(elems) => any(elems),
+            TypeRef::List(ty) | TypeRef::Array(ty) | TypeRef::Option(ty) | TypeRef::Set(ty) => {
+                ty.uses_type_param(name)
+            }
+            TypeRef::Map(k, v) | TypeRef::Result(k, v) => {
+                k.uses_type_param(name) || v.uses_type_param(name)
+            }
+            TypeRef::Struct(fields) => fields.iter().any(|(_, ty)| ty.uses_type_param(name)),
+            TypeRef::Verbatim(source) => {
+                let param = format!("'{}", name);
+                source.match_indices(&param).any(|(i, _)| {
+                    !source[i + param.len()..]
+                        .starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '\'')
+                })
+            }
+            TypeRef::Primitive(_) | TypeRef::Unsupported(_) => false,
+        }
+    }
+
+    pub fn option(ty: TypeRef) -> Self {
+        TypeRef::Option(Box::new(ty))
+    }
+
+    pub fn list(ty: TypeRef) -> Self {
+        TypeRef::List(Box::new(ty))
+    }
+
+    pub fn map(k: TypeRef, v: TypeRef) -> Self {
+        TypeRef::Map(Box::new(k), Box::new(v))
+    }
+}
+
+/// The builtin types, named after their FSharp counterparts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Primitive {
+    Bool,
+    Byte,
+    SByte,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    BigInt,
+    Float32,
+    Float,
+    String,
+    Unit,
+    /// any JSON value e.g. `serde_json::Value`
+    Obj,
+}
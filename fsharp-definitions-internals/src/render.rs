@@ -0,0 +1,876 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The FSharp backend: writes a [`TypeDefinition`] as definitions
+//! FSharp.Json reads and writes the same JSON as serde_json.
+
+use crate::derive_struct::empty_tuple_shape;
+use crate::flatten_marker;
+use crate::fs_ident::{dotnet_name, fs_case_ident, fs_ident};
+use crate::model::{
+    Case, CaseFields, Factory, Field, Handler, Primitive, Tagging, TypeBody, TypeDefinition,
+    TypeRef, Union, UnionFields,
+};
+use crate::source_builder::SourceBuilder;
+
+impl TypeDefinition {
+    /// the FSharp type name, with its type parameters
+    fn fs_name(&self) -> String {
+        format!("{}{}", fs_ident(&self.name), fs_generics(&self.type_params))
+    }
+
+    /// the FSharp type name of the alias for a variant's content:
+    /// the container name followed by the variant name, with only the type
+    /// parameters the content uses (an abbreviation must use all of them)
+    fn case_alias(&self, case: &Case) -> String {
+        let unraw = |name: &str| name.trim_start_matches("r#").to_string();
+        let type_params = self
+            .type_params
+            .iter()
+            .filter(|p| case.fields.uses_type_param(p))
+            .cloned()
+            .collect::<Vec<_>>();
+        format!(
+            "{}{}",
+            fs_ident(&(unraw(&self.name) + &unraw(&case.name))),
+            fs_generics(&type_params)
+        )
+    }
+}
+
+/// `'T` for a rust generic `T`
+fn fs_type_param(name: &str) -> String {
+    format!("'{}", name)
+}
+
+/// `<'T, 'U>` for a generic enum or struct, empty otherwise.
+fn fs_generics(type_params: &[String]) -> String {
+    if type_params.is_empty() {
+        return String::new();
+    }
+    let params = type_params
+        .iter()
+        .map(|p| fs_type_param(p))
+        .collect::<Vec<_>>();
+    format!("<{}>", params.join(", "))
+}
+
+fn primitive(p: Primitive) -> &'static str {
+    match p {
+        Primitive::Bool => "bool",
+        Primitive::Byte => "byte",
+        Primitive::SByte => "sbyte",
+        Primitive::Int16 => "int16",
+        Primitive::UInt16 => "uint16",
+        Primitive::Int32 => "int32",
+        Primitive::UInt32 => "uint32",
+        Primitive::Int64 => "int64",
+        Primitive::UInt64 => "uint64",
+        Primitive::BigInt => "bigint",
+        Primitive::Float32 => "float32",
+        Primitive::Float => "float",
+        Primitive::String => "string",
+        Primitive::Unit => "unit",
+        Primitive::Obj => "obj",
+    }
+}
+
+/// the FSharp type for `ty`
+pub(crate) fn fs_type(ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::Primitive(p) => primitive(*p).to_string(),
+        TypeRef::TypeParam(name) => fs_type_param(name),
+        TypeRef::Named { name, args } if args.is_empty() => fs_ident(name),
+        TypeRef::Named { name, args } => format!("{}<{}>", fs_ident(name), fs_types(args)),
+        // `()` serializes as `null`
+        TypeRef::Tuple(elems) if elems.is_empty() => primitive(Primitive::Unit).to_string(),
+        TypeRef::Tuple(elems) => elems
+            .iter()
+            .map(fs_type_operand)
+            .collect::<Vec<_>>()
+            .join(" * "),
+        TypeRef::List(t) => format!("{} list", fs_type_operand(t)),
+        TypeRef::Array(t) => format!("{}[]", fs_type_operand(t)),
+        TypeRef::Option(t) => format!("{} option", fs_type_operand(t)),
+        TypeRef::Map(k, v) => format!("Map<{}, {}>", fs_type(k), fs_type(v)),
+        TypeRef::Set(t) => format!("Set<{}>", fs_type(t)),
+        TypeRef::Result(k, v) => format!("RsResult<{}, {}>", fs_type(k), fs_type(v)),
+        TypeRef::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(name, t)| format!("{}: {}", fs_ident(name), fs_type(t)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", fields.join("; "))
+        }
+        TypeRef::Verbatim(s) => s.clone(),
+        TypeRef::Unsupported(what) => format!("(* SourcePart todo: {} *)", what),
+    }
+}
+
+fn fs_types(types: &[TypeRef]) -> String {
+    types.iter().map(fs_type).collect::<Vec<_>>().join(", ")
+}
+
+/// `ty` as the operand of a postfix type (`list`, `option`, `[]`) or a
+/// tuple element: tuples (and `#[fs(fs_type)]`s that might be) need parentheses.
+fn fs_type_operand(ty: &TypeRef) -> String {
+    let parens = match ty {
+        TypeRef::Tuple(elems) => elems.len() > 1,
+        TypeRef::Verbatim(s) => s.contains('*') || s.contains("->"),
+        _ => false,
+    };
+    if parens {
+        format!("({})", fs_type(ty))
+    } else {
+        fs_type(ty)
+    }
+}
+
+fn doc_source(doc: &[String]) -> SourceBuilder {
+    let mut src = SourceBuilder::default();
+    for line in doc {
+        if line.is_empty() {
+            // separates doc comments that were merged
+            src.ln_push("///");
+        } else {
+            src.ln_push("/// ");
+            src.push(line);
+        }
+    }
+    src
+}
+
+/// the type of the record label for `field`
+fn field_type(field: &Field) -> TypeRef {
+    match field.ty {
+        // a flattened map: FSharp.Json doesn't collect the other keys so it is always missing
+        ref map if field.flatten && !matches!(map, TypeRef::Named { .. }) => {
+            TypeRef::option(map.clone())
+        }
+        ref ty => ty.clone(),
+    }
+}
+
+/// `label: ty;` for a record field, with any FSharp.Json attributes it needs.
+fn field_source(field: &Field) -> SourceBuilder {
+    let mut source = SourceBuilder::default();
+    if field.flatten {
+        match field.ty {
+            // the organizer replaces the marker with the fields of the struct
+            TypeRef::Named { ref name, .. } => source.ln_push(&flatten_marker(name)),
+            _ => {
+                source.ln_note("derive_flatten_field ♣︎");
+                source.ln_push(
+                    "/// all other fields (`#[serde(flatten)]`): FSharp.Json doesn't collect these",
+                );
+                source.ln_push(&fs_ident(&field.name));
+                source.push(": ");
+                source.push(&fs_type(&field_type(field)));
+                source.push(";");
+            }
+        }
+        return source;
+    }
+    let label = fs_ident(&field.name);
+    let mut json_field = vec![];
+    if dotnet_name(&label) != field.serde_name {
+        // the wire name has to come first
+        json_field.push(format!("\"{}\"", field.serde_name));
+    }
+    if let Some(ref transform) = field.transform {
+        json_field.push(format!("Transform=typeof<{}>", transform));
+    }
+    source.ln_note("derive_field ♠︎");
+    source.push_source(doc_source(&field.doc));
+    if !json_field.is_empty() {
+        source.ln_push(&format!("[<JsonField({})>]", json_field.join(", ")));
+    }
+    source.ln_push(&label);
+    source.push(": ");
+    source.push(&fs_type(&field.ty));
+    source.push(";");
+    source
+}
+
+/// the union case for a variant: the rust identifier, with a
+/// `[<JsonUnionCase("...")>]` if serde renames it.
+fn case_name(name: &str, serde_name: &str) -> String {
+    let ident = fs_case_ident(name);
+    if dotnet_name(&ident) == serde_name {
+        ident
+    } else {
+        format!("[<JsonUnionCase(\"{}\")>] {}", serde_name, ident)
+    }
+}
+
+/// the record fields of a struct, one per line without indentation,
+/// for types that `#[serde(flatten)]` it
+pub(crate) fn fields_source(def: &TypeDefinition) -> Option<String> {
+    match def.body {
+        TypeBody::Record(ref fields) => {
+            let mut source = SourceBuilder::default();
+            for field in fields {
+                source.push_source(field_source(field));
+            }
+            Some(source.finish().trim_start_matches('\n').to_string())
+        }
+        _ => None,
+    }
+}
+
+/// the FSharp definition of `def`: a type alias, a record or a union
+/// preceded by the aliases for its variants.
+pub(crate) fn definition_source(def: &TypeDefinition) -> SourceBuilder {
+    let mut type_src = SourceBuilder::default();
+    type_src.ln_push("");
+    let body = match def.body {
+        TypeBody::Alias(ref ty) => SourceBuilder::simple(&fs_type(ty)),
+        TypeBody::Record(ref fields) => {
+            let mut source = SourceBuilder::default();
+            source.push("{ ");
+            for field in fields {
+                source.push_source_1(field_source(field));
+            }
+            source.push(" }");
+            source
+        }
+        TypeBody::Union(ref union) => {
+            let mut aliases = SourceBuilder::default();
+            let mut cases = SourceBuilder::default();
+            for case in &union.cases {
+                cases.ln_note("variant source ✈︎");
+                cases.ln_note(match case.fields {
+                    CaseFields::Unit => "unit variant ☉",
+                    CaseFields::Newtype(_) => "newtype variant ☂︎",
+                    CaseFields::Tuple(_) => "tuple variant ⚃",
+                    CaseFields::Struct(_) => "struct variant ♛",
+                });
+                cases.push_source(doc_source(&case.doc));
+                cases.ln_push("| ");
+                cases.push(&case_name(&case.name, &case.serde_name));
+                match payload(def, union, case) {
+                    Payload::Empty => (),
+                    Payload::Single(ty) => {
+                        cases.push(" of ");
+                        cases.push(&ty);
+                    }
+                    Payload::Fields(fields) => {
+                        let fields = fields
+                            .iter()
+                            .map(|(label, _, ty)| format!("{}: {}", label, ty))
+                            .collect::<Vec<_>>();
+                        cases.push(" of ");
+                        cases.push(&fields.join(" * "));
+                    }
+                }
+                if union.union_fields == UnionFields::Alias && case.fields != CaseFields::Unit {
+                    aliases.ln_note("variant ☀︎");
+                    aliases.push_source(doc_source(&case.doc));
+                    aliases.ln_push("type ");
+                    aliases.push(&def.case_alias(case));
+                    aliases.push(" = ");
+                    aliases.push_source_1(alias_source(&case.fields));
+                }
+            }
+            type_src.ln_note("top level definitions ✎");
+            type_src.push_source(aliases);
+            cases
+        }
+    };
+
+    type_src.push_source(doc_source(&def.doc));
+    if let TypeBody::Union(ref union) = def.body {
+        match union.tagging {
+            Tagging::Adjacent {
+                ref tag,
+                ref content,
+            } => {
+                type_src.ln_push(&format!("[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"{}\", CaseValueField=\"{}\")>]", tag, content));
+            }
+            Tagging::Internal { ref tag } => {
+                // FSharp.Json merges the fields of the (single) record case value with the tag
+                type_src.ln_push(&format!(
+                    "[<JsonUnion(Mode = UnionMode.CaseKeyDiscriminatorField, CaseKeyField=\"{}\")>]",
+                    tag
+                ));
+            }
+            Tagging::External => {
+                // FSharp.Json writes cases without fields as bare strings, like serde
+                type_src.ln_push("[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>]");
+            }
+            Tagging::Untagged => (),
+        }
+    }
+
+    type_src.ln_push("type ");
+    type_src.push(&def.fs_name());
+    type_src.push(" =");
+    type_src.push_source_1(body);
+    if let TypeBody::Union(ref union) = def.body {
+        let handler = union
+            .handler
+            .as_ref()
+            .filter(|_| !union.cases.is_empty())
+            .map(|handler| handler_source(def, union, handler));
+        if let Some((ref handler_type, _)) = handler {
+            type_src.push_source(handler_type.clone());
+        }
+        let mut modules = SourceBuilder::default();
+        match union.tagging {
+            Tagging::Untagged => modules.push_source(untagged_decoder(def)),
+            Tagging::Internal { ref tag } if !union.cases.iter().all(is_record_case) => {
+                modules.push_source(internal_converter(def, union, tag))
+            }
+            Tagging::External if union.union_fields == UnionFields::Inline => {
+                modules.push_source(external_converter(def, union))
+            }
+            _ => (),
+        }
+        if let Some((_, handler_module)) = handler {
+            modules.push_source(handler_module);
+        }
+        if let Some(ref factory) = union.factory {
+            modules.push_source(factory_source(def, union, factory));
+        }
+        if modules.is_not_empty() {
+            type_src.ln_note("top level modules ✎");
+            type_src.push_source(modules);
+        }
+    }
+    type_src
+}
+
+/// the type of the `type EnumV = ...` alias for the content of a variant
+fn alias_source(fields: &CaseFields) -> SourceBuilder {
+    match fields {
+        CaseFields::Unit => SourceBuilder::simple(&fs_type(&TypeRef::Tuple(vec![]))),
+        CaseFields::Newtype(ty) => SourceBuilder::simple(&fs_type(ty)),
+        // `B()` is still an (empty) array
+        CaseFields::Tuple(elems) if elems.is_empty() => {
+            SourceBuilder::simple(&fs_type(&empty_tuple_shape()))
+        }
+        CaseFields::Tuple(elems) => SourceBuilder::simple(&fs_type(&TypeRef::Tuple(elems.clone()))),
+        CaseFields::Struct(fields) => {
+            let mut record = SourceBuilder::default();
+            record.push("{");
+            for field in fields {
+                record.push_source_1(field_source(field));
+            }
+            record.ln_push("}");
+            record
+        }
+    }
+}
+
+/// How the content of a variant is written in its union case.
+enum Payload {
+    /// a unit variant
+    Empty,
+    /// `| V of T`: one field, the alias or the type itself
+    Single(String),
+    /// `| V of foo: bool * bar: int64`: (label, serde name, type) of each field
+    Fields(Vec<(String, String, String)>),
+}
+
+fn payload(def: &TypeDefinition, union: &Union, case: &Case) -> Payload {
+    match (&case.fields, union.union_fields) {
+        (CaseFields::Unit, _) => Payload::Empty,
+        (_, UnionFields::Alias) => Payload::Single(def.case_alias(case)),
+        // a tuple type would be several fields
+        (CaseFields::Newtype(ty), _) => Payload::Single(fs_type_operand(ty)),
+        (CaseFields::Tuple(elems), _) if elems.is_empty() => {
+            Payload::Single(fs_type(&empty_tuple_shape()))
+        }
+        (CaseFields::Tuple(elems), _) => {
+            Payload::Single(fs_type_operand(&TypeRef::Tuple(elems.clone())))
+        }
+        (CaseFields::Struct(fields), UnionFields::Anonymous) => {
+            Payload::Single(anonymous_record(&case_fields(fields)))
+        }
+        (CaseFields::Struct(fields), UnionFields::Inline) => Payload::Fields(case_fields(fields)),
+    }
+}
+
+/// (label, serde name, type) of the fields of a struct variant
+fn case_fields(fields: &[Field]) -> Vec<(String, String, String)> {
+    fields
+        .iter()
+        .map(|field| {
+            (
+                fs_ident(&field.name),
+                field.serde_name.clone(),
+                fs_type_operand(&field_type(field)),
+            )
+        })
+        .collect()
+}
+
+/// `{| foo: bool; bar: int64 |}`: anonymous records can't have `JsonField`
+/// attributes so the labels are the serde names.
+fn anonymous_record(fields: &[(String, String, String)]) -> String {
+    let fields = fields
+        .iter()
+        .map(|(_, serde_name, ty)| format!("{}: {}", fs_ident(serde_name), ty))
+        .collect::<Vec<_>>();
+    format!("{{| {} |}}", fields.join("; "))
+}
+
+/// `(f1, f2)`: a pattern for the fields of an inline case
+fn fields_pattern(count: usize) -> Vec<String> {
+    (1..=count).map(|i| format!("f{}", i)).collect()
+}
+
+fn tuple_pattern(names: &[String]) -> String {
+    if names.len() == 1 {
+        names[0].clone()
+    } else {
+        format!("({})", names.join(", "))
+    }
+}
+
+/// `#[fs(handler_name = "...")]`: a record with a function for each variant
+/// and a module with a `dispatch` that calls the function for a value. The
+/// record has to be built with all the functions so adding a variant breaks
+/// the code that handles the union.
+fn handler_source(
+    def: &TypeDefinition,
+    union: &Union,
+    handler: &Handler,
+) -> (SourceBuilder, SourceBuilder) {
+    let name = fs_ident(&handler.name);
+    let generics = fs_generics(&def.type_params);
+    let mut src = SourceBuilder::default();
+    src.ln_note("handler ☎︎");
+    src.ln_push(&format!(
+        "/// a function for each variant of `{}`",
+        fs_ident(&def.name)
+    ));
+    src.ln_push(&format!("type {}{} =", name, generics));
+    for (i, case) in union.cases.iter().enumerate() {
+        let open = if i == 0 { "{ " } else { "  " };
+        // the fields of an inline case are curried
+        let arguments = match payload(def, union, case) {
+            Payload::Empty => primitive(Primitive::Unit).to_string(),
+            Payload::Single(ty) => ty,
+            Payload::Fields(fields) => fields
+                .into_iter()
+                .map(|(_, _, ty)| ty)
+                .collect::<Vec<_>>()
+                .join(" -> "),
+        };
+        src.ln_push_1(&format!(
+            "{}{}: {} -> {}",
+            open,
+            fs_case_ident(&case.name),
+            arguments,
+            handler.return_type
+        ));
+    }
+    src.push(" }");
+
+    let mut module = SourceBuilder::default();
+    module.ln_push(&format!("module {} =", name));
+    let mut dispatch = SourceBuilder::default();
+    dispatch.ln_push("/// call the `handler` function for the variant of `value`");
+    dispatch.ln_push(&format!(
+        "let dispatch{} (handler: {}{}) (value: {}) : {} =",
+        generics,
+        name,
+        generics,
+        def.fs_name(),
+        handler.return_type
+    ));
+    dispatch.ln_push_1("match value with");
+    for case in &union.cases {
+        let name = fs_case_ident(&case.name);
+        dispatch.ln_push_1(&match payload(def, union, case) {
+            Payload::Empty => format!("| {} -> handler.{} ()", name, name),
+            Payload::Single(_) => format!("| {} v -> handler.{} v", name, name),
+            Payload::Fields(fields) => {
+                let names = fields_pattern(fields.len());
+                format!(
+                    "| {} {} -> handler.{} {}",
+                    name,
+                    tuple_pattern(&names),
+                    name,
+                    names.join(" ")
+                )
+            }
+        });
+    }
+    module.push_source_1(dispatch);
+    (src, module)
+}
+
+/// `#[fs(factory_name = "...")]`: a module with a (curried) function for each
+/// variant that constructs it. The fields of struct variants are labeled parameters.
+fn factory_source(def: &TypeDefinition, union: &Union, factory: &Factory) -> SourceBuilder {
+    let generics = fs_generics(&def.type_params);
+    let return_type = factory.return_type.clone().unwrap_or_else(|| def.fs_name());
+    let mut functions = SourceBuilder::default();
+    for case in &union.cases {
+        let name = fs_case_ident(&case.name);
+        // `Open` => `open` (quoted: it's a keyword)
+        let mut chars = case.name.trim_start_matches("r#").chars();
+        let function = fs_ident(&match chars.next() {
+            Some(first) => first.to_lowercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        });
+        let (params, value) = match case.fields {
+            CaseFields::Unit => (vec!["()".to_string()], name),
+            CaseFields::Newtype(_) => match payload(def, union, case) {
+                Payload::Single(ty) => {
+                    (vec![format!("(value: {})", ty)], format!("{} value", name))
+                }
+                _ => unreachable!("newtype variants have one field"),
+            },
+            CaseFields::Tuple(ref elems) if elems.is_empty() => {
+                (vec!["()".to_string()], format!("{} []", name))
+            }
+            CaseFields::Tuple(ref elems) => {
+                let items = (1..=elems.len())
+                    .map(|i| format!("item{}", i))
+                    .collect::<Vec<_>>();
+                let params = items
+                    .iter()
+                    .zip(elems)
+                    .map(|(item, ty)| format!("({}: {})", item, fs_type(ty)))
+                    .collect();
+                (params, format!("{} ({})", name, items.join(", ")))
+            }
+            CaseFields::Struct(ref fields) => {
+                let labels = fields
+                    .iter()
+                    .map(|field| (fs_ident(&field.name), field_type(field)))
+                    .collect::<Vec<_>>();
+                let params = labels
+                    .iter()
+                    .map(|(label, ty)| format!("({}: {})", label, fs_type(ty)))
+                    .collect();
+                let value = match union.union_fields {
+                    UnionFields::Alias => {
+                        let assign = labels
+                            .iter()
+                            .map(|(label, _)| format!("{} = {}", label, label))
+                            .collect::<Vec<_>>();
+                        format!("({{ {} }} : {})", assign.join("; "), def.case_alias(case))
+                    }
+                    UnionFields::Anonymous => {
+                        let assign = labels
+                            .iter()
+                            .zip(fields)
+                            .map(|((label, _), field)| {
+                                format!("{} = {}", fs_ident(&field.serde_name), label)
+                            })
+                            .collect::<Vec<_>>();
+                        format!("{{| {} |}}", assign.join("; "))
+                    }
+                    UnionFields::Inline => {
+                        let labels = labels
+                            .into_iter()
+                            .map(|(label, _)| label)
+                            .collect::<Vec<_>>();
+                        tuple_pattern(&labels)
+                    }
+                };
+                (params, format!("{} {}", name, value))
+            }
+        };
+        functions.push_source(doc_source(&case.doc));
+        functions.ln_push(&format!(
+            "let {}{} {} : {} = {}",
+            function,
+            generics,
+            params.join(" "),
+            return_type,
+            value
+        ));
+    }
+    let mut module = SourceBuilder::default();
+    module.ln_push(&format!("module {} =", fs_ident(&factory.name)));
+    module.push_source_1(functions);
+    module
+}
+
+/// `module Enum = let decode ... let encode ...` for externally tagged enums
+/// with inline fields: FSharp.Json writes a case with several fields as an
+/// array where serde writes the object of a struct variant.
+fn external_converter(def: &TypeDefinition, union: &Union) -> SourceBuilder {
+    let ident = fs_ident(&def.name);
+    let generics = fs_generics(&def.type_params);
+    let mut src = SourceBuilder::default();
+    src.ln_push(&format!("module {} =", ident));
+
+    let unknown = format!(
+        "| other -> Error (sprintf \"unknown variant of {}: %s\" other)",
+        ident
+    );
+    let mut decode = SourceBuilder::default();
+    decode.ln_push(&format!(
+        "/// decode an externally tagged `{}`: a unit variant's name or `{{ \"Variant\": content }}`",
+        ident
+    ));
+    decode.ln_push(&format!(
+        "let decode{} (json: string) : Result<{}{}, string> =",
+        generics, ident, generics
+    ));
+    decode.ln_push_1("try");
+    decode.ln_push_1("  use doc = System.Text.Json.JsonDocument.Parse json");
+    decode.ln_push_1("  let root = doc.RootElement");
+    decode.ln_push_1("  if root.ValueKind = System.Text.Json.JsonValueKind.String then");
+    decode.ln_push_1("    match root.GetString() with");
+    for case in &union.cases {
+        if case.fields == CaseFields::Unit {
+            decode.ln_push_1(&format!(
+                "    | \"{}\" -> Ok {}",
+                case.serde_name,
+                fs_case_ident(&case.name)
+            ));
+        }
+    }
+    decode.ln_push_1(&format!("    {}", unknown));
+    decode.ln_push_1("  else");
+    decode.ln_push_1("    let case = root.EnumerateObject() |> Seq.exactlyOne");
+    decode.ln_push_1("    let content = case.Value.GetRawText()");
+    decode.ln_push_1("    match case.Name with");
+    for case in &union.cases {
+        let name = fs_case_ident(&case.name);
+        let value = match payload(def, union, case) {
+            Payload::Empty => continue,
+            Payload::Single(ty) => format!("({} (Json.deserialize<{}> content))", name, ty),
+            // read the fields as an anonymous record
+            Payload::Fields(fields) => {
+                let values = fields
+                    .iter()
+                    .map(|(_, serde_name, _)| format!("v.{}", fs_ident(serde_name)))
+                    .collect::<Vec<_>>();
+                format!(
+                    "(let v = Json.deserialize<{}> content in {} {})",
+                    anonymous_record(&fields),
+                    name,
+                    tuple_pattern(&values)
+                )
+            }
+        };
+        decode.ln_push_1(&format!("    | \"{}\" -> Ok {}", case.serde_name, value));
+    }
+    decode.ln_push_1(&format!("    {}", unknown));
+    decode.ln_push_1("with e -> Error e.Message");
+
+    let mut encode = SourceBuilder::default();
+    encode.ln_push(&format!(
+        "/// encode an externally tagged `{}` as serde does",
+        ident
+    ));
+    encode.ln_push(&format!(
+        "let encode{} (value: {}{}) : string =",
+        generics, ident, generics
+    ));
+    encode.ln_push_1("match value with");
+    for case in &union.cases {
+        let name = fs_case_ident(&case.name);
+        let key = fs_ident(&case.serde_name);
+        match payload(def, union, case) {
+            Payload::Empty => {
+                encode.ln_push_1(&format!("| {} -> \"\\\"{}\\\"\"", name, case.serde_name))
+            }
+            Payload::Single(_) => encode.ln_push_1(&format!(
+                "| {} v -> Json.serialize {{| {} = v |}}",
+                name, key
+            )),
+            Payload::Fields(fields) => {
+                let names = fields_pattern(fields.len());
+                let record = fields
+                    .iter()
+                    .zip(&names)
+                    .map(|((_, serde_name, _), name)| {
+                        format!("{} = {}", fs_ident(serde_name), name)
+                    })
+                    .collect::<Vec<_>>();
+                encode.ln_push_1(&format!(
+                    "| {} {} -> Json.serialize {{| {} = {{| {} |}} |}}",
+                    name,
+                    tuple_pattern(&names),
+                    key,
+                    record.join("; ")
+                ))
+            }
+        }
+    }
+
+    src.push_source_1(decode);
+    src.push_source_1(encode);
+    src
+}
+
+/// FSharp.Json's `CaseKeyDiscriminatorField` mode can only merge the tag
+/// into a case holding a single record
+fn is_record_case(case: &Case) -> bool {
+    match case.fields {
+        CaseFields::Unit => false,
+        CaseFields::Newtype(ref ty) => matches!(ty, TypeRef::Named { .. }),
+        CaseFields::Tuple(_) | CaseFields::Struct(_) => true,
+    }
+}
+
+/// `module Enum = let decode ... let encode ...` for internally tagged enums
+/// with unit variants or newtypes of maps etc. that FSharp.Json can't tag
+fn internal_converter(def: &TypeDefinition, union: &Union, tag: &str) -> SourceBuilder {
+    let ident = fs_ident(&def.name);
+    let generics = fs_generics(&def.type_params);
+    let mut src = SourceBuilder::default();
+    src.ln_push(&format!("module {} =", ident));
+
+    let mut decode = SourceBuilder::default();
+    decode.ln_push(&format!(
+        "/// decode an internally tagged `{}` by its \"{}\" field",
+        ident, tag
+    ));
+    decode.ln_push(&format!(
+        "let decode{} (json: string) : Result<{}{}, string> =",
+        generics, ident, generics
+    ));
+    decode.ln_push_1("try");
+    decode.ln_push_1(&format!(
+        "  match (Json.deserialize<{{| {}: string |}}> json).{} with",
+        fs_ident(tag),
+        fs_ident(tag)
+    ));
+    for case in &union.cases {
+        let name = fs_case_ident(&case.name);
+        let value = match payload(def, union, case) {
+            Payload::Empty => name,
+            Payload::Single(ty) => format!("({} (Json.deserialize<{}> json))", name, ty),
+            Payload::Fields(_) => {
+                unreachable!("inline union fields need an externally tagged enum")
+            }
+        };
+        decode.ln_push_1(&format!("  | \"{}\" -> Ok {}", case.serde_name, value));
+    }
+    decode.ln_push_1(&format!(
+        "  | other -> Error (sprintf \"unknown variant of {}: %s\" other)",
+        ident
+    ));
+    decode.ln_push_1("with e -> Error e.Message");
+
+    let mut encode = SourceBuilder::default();
+    encode.ln_push(&format!(
+        "/// encode an internally tagged `{}`, adding the \"{}\" field to the variant's content",
+        ident, tag
+    ));
+    encode.ln_push(&format!(
+        "let encode{} (value: {}{}) : string =",
+        generics, ident, generics
+    ));
+    // `tagged` gets the opening of the object, e.g. `{"type":"A"`
+    encode.ln_push_1("let tagged (opening: string) (content: string) =");
+    encode.ln_push_1("  match content.Trim().Substring(1).TrimStart() with");
+    encode.ln_push_1("  | fields when fields.StartsWith \"}\" -> opening + \"}\"");
+    encode.ln_push_1("  | fields -> opening + \",\" + fields");
+    encode.ln_push_1("match value with");
+    for case in &union.cases {
+        let name = fs_case_ident(&case.name);
+        let opening = format!("\"{{\\\"{}\\\":\\\"{}\\\"\"", tag, case.serde_name);
+        match payload(def, union, case) {
+            Payload::Empty => encode.ln_push_1(&format!("| {} -> {} + \"}}\"", name, opening)),
+            Payload::Single(_) => encode.ln_push_1(&format!(
+                "| {} v -> tagged {} (Json.serialize v)",
+                name, opening
+            )),
+            Payload::Fields(_) => {
+                unreachable!("inline union fields need an externally tagged enum")
+            }
+        }
+    }
+
+    src.push_source_1(decode);
+    src.push_source_1(encode);
+    src
+}
+
+/// FSharp.Json has no untagged union mode so we generate
+/// `decode` (and `encode`) functions that, like serde, try each
+/// variant shape in declaration order.
+fn untagged_decoder(def: &TypeDefinition) -> SourceBuilder {
+    let cases = match def.body {
+        TypeBody::Union(ref union) => union
+            .cases
+            .iter()
+            .map(|case| (fs_case_ident(&case.name), payload(def, union, case)))
+            .collect::<Vec<_>>(),
+        _ => vec![],
+    };
+    let ident = fs_ident(&def.name);
+    let generics = fs_generics(&def.type_params);
+    let mut src = SourceBuilder::default();
+    src.ln_push(&format!("module {} =", ident));
+
+    let mut decode = SourceBuilder::default();
+    decode.ln_push(&format!(
+        "/// decode an untagged `{}`, trying each variant in declaration order",
+        ident
+    ));
+    decode.ln_push(&format!(
+        "let decode{} (json: string) : Result<{}{}, string> =",
+        generics, ident, generics
+    ));
+    decode.ln_push_1(&format!(
+        "let attempts : (string * (unit -> {}{})) list =",
+        ident, generics
+    ));
+    for (i, (case, payload)) in cases.iter().enumerate() {
+        let attempt = match payload {
+            Payload::Single(ty) => format!("(fun () -> {} (Json.deserialize<{}> json))", case, ty),
+            Payload::Fields(_) => {
+                unreachable!("inline union fields need an externally tagged enum")
+            }
+            // untagged unit variants are `null`
+            Payload::Empty => format!(
+                "(fun () -> if json.Trim() = \"null\" then {} else failwith \"expected null\")",
+                case
+            ),
+        };
+        let open = if i == 0 { "[ " } else { "  " };
+        decode.ln_push_1(&format!("  {}\"{}\", {}", open, case, attempt));
+    }
+    if cases.is_empty() {
+        decode.ln_push_1("  [");
+    }
+    decode.push(" ]");
+    decode.ln_push_1("let rec attempt errors attempts =");
+    decode.ln_push_1("  match attempts with");
+    decode.ln_push_1(&format!("  | [] -> Error (sprintf \"data did not match any variant of untagged enum {}: %s\" (String.concat \"; \" (List.rev errors)))", ident));
+    decode.ln_push_1("  | (name, decoder) :: rest ->");
+    decode.ln_push_1("    try Ok (decoder ())");
+    decode.ln_push_1("    with e -> attempt (sprintf \"%s: %s\" name e.Message :: errors) rest");
+    decode.ln_push_1("attempt [] attempts");
+
+    let mut encode = SourceBuilder::default();
+    encode.ln_push(&format!(
+        "/// encode an untagged `{}` as just the variant's content",
+        ident
+    ));
+    encode.ln_push(&format!(
+        "let encode{} (value: {}{}) : string =",
+        generics, ident, generics
+    ));
+    encode.ln_push_1("match value with");
+    for (case, payload) in &cases {
+        match payload {
+            Payload::Single(_) => encode.ln_push_1(&format!("| {} v -> Json.serialize v", case)),
+            Payload::Fields(_) => {
+                unreachable!("inline union fields need an externally tagged enum")
+            }
+            Payload::Empty => encode.ln_push_1(&format!("| {} -> \"null\"", case)),
+        }
+    }
+
+    src.push_source_1(decode);
+    src.push_source_1(encode);
+    src
+}
@@ -46,11 +46,11 @@ impl SourceBuilder {
         self.code.push_str(s);
     }
     pub fn ln_push(&mut self, s: &str) {
-        self.code.push_str("\n");
+        self.code.push('\n');
         self.code.push_str(s);
     }
     pub fn ln_push_1(&mut self, s: &str) {
-        self.code.push_str("\n");
+        self.code.push('\n');
         self.code.push_str(&self.indent);
         self.code.push_str(s);
     }
@@ -0,0 +1,1408 @@
+#[allow(unused_imports)]
+use super::FSharpify;
+
+#[cfg(test)]
+mod macro_test {
+    use super::FSharpify;
+    use quote::quote;
+
+    macro_rules! assert_conversion {
+        ($tokens:expr,$expected:expr) => {{
+            let source = FSharpify::new($tokens)
+                .parse()
+                .unwrap()
+                .export_type_definition_source()
+                .finish();
+
+            assert_eq!(without_notes(&source), $expected)
+        }};
+    }
+
+    /// strip `// NOTE:` lines and blank lines the same way
+    /// `fsharp_definitions::organize` does.
+    fn without_notes(source: &str) -> String {
+        source
+            .lines()
+            .map(|ln| ln.trim_end())
+            .filter(|ln| !ln.is_empty() && !ln.trim_start().starts_with("// NOTE: "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // The crate only converts complex types, so we need to wrap the type in a struct to check its
+    // conversion. We could also write these tests at the conversion logic inside the crate but
+    // right now I'm just tring to get as much value for as little effort as possible.
+    macro_rules! assert_type_conversion {
+        ($rust:ty,$fs:literal) => {
+            assert_conversion!(
+                struct_with_type!($rust),
+                format!("type Test ={{\n    t: {}; }}", $fs)
+            )
+        };
+    }
+
+    macro_rules! struct_with_type {
+        ($type:ty) => {
+            quote!(
+                struct Test {
+                    t: $type,
+                }
+            )
+        };
+    }
+
+    // Untested Conversions:
+    //  Path, PathBuf
+    //  Box, Cow, Rc, Arc, Cell, RefCell
+    //  Duration
+    //  SystemTime
+    //  VecDeque, LinkedList
+    //  BTreeMap, BTreeSet
+    //  Fn, FnOnce, FnMut
+    //  TraitsObject, ImplTrait
+    //  Literally every other type
+
+    #[test]
+    fn boolean_type_conversion() {
+        assert_type_conversion!(bool, "bool");
+    }
+
+    #[test]
+    fn numeric_type_conversions() {
+        assert_type_conversion!(i8, "sbyte");
+        assert_type_conversion!(i16, "int16");
+        assert_type_conversion!(i32, "int32");
+        assert_type_conversion!(i64, "int64");
+        assert_type_conversion!(i128, "bigint");
+        assert_type_conversion!(isize, "int64");
+
+        assert_type_conversion!(u8, "byte");
+        assert_type_conversion!(u16, "uint16");
+        assert_type_conversion!(u32, "uint32");
+        assert_type_conversion!(u64, "uint64");
+        assert_type_conversion!(u128, "bigint");
+        assert_type_conversion!(usize, "uint64");
+
+        assert_type_conversion!(f32, "float32");
+        assert_type_conversion!(f64, "float");
+    }
+
+    #[test]
+    fn widen_numbers() {
+        let tokens = quote!(
+            #[fs(widen_numbers)]
+            struct Widened {
+                a: u8,
+                b: Option<u64>,
+                c: f32,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Widened ={\n    a: int64;\n    b: int64 option;\n    c: float; }"
+        );
+        let tokens = quote!(
+            struct PerField {
+                a: u8,
+                #[fs(widen_numbers)]
+                b: Vec<u32>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type PerField ={\n    a: byte;\n    b: int64 list; }"
+        );
+    }
+
+    #[test]
+    fn fs_as() {
+        let tokens = quote!(
+            struct Chrono {
+                #[fs(fs_as = "str")]
+                utc: DateTime<Utc>,
+                #[fs(ts_as = "Vec<u8>")]
+                ip: ArrayVec<[u8; 4]>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Chrono ={\n    utc: string;\n    ip: byte list; }"
+        );
+
+        let tokens = quote!(
+            #[fs(fs_as = "String")]
+            enum Id {
+                Number(u64),
+                Name { first: String, last: String },
+            }
+        );
+        assert_conversion!(tokens, "type Id =string");
+
+        // the old spelling still works on the container, with a warning
+        let tokens = quote!(
+            #[fs(ts_as = "String")]
+            struct Id(u64);
+        );
+        assert_conversion!(tokens.clone(), "type Id =string");
+        let input: syn::DeriveInput = syn::parse2(tokens).unwrap();
+        assert_eq!(crate::attrs::deprecated_ts_as(&input).len(), 1);
+
+        let tokens = quote!(
+            struct S {
+                #[fs(fs_as = "😀i32>")]
+                a: i32,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "fs_as: \"😀i32>\" is not a valid rust type"
+        );
+
+        let input: syn::DeriveInput = syn::parse2(quote!(
+            #[fs(fs_as = "String")]
+            enum E {
+                A(#[fs(ts_as = "String")] Name),
+                B {
+                    #[fs(fs_as = "String", ts_as = "String")]
+                    b: Name,
+                },
+            }
+        ))
+        .unwrap();
+        assert_eq!(crate::attrs::deprecated_ts_as(&input).len(), 2);
+    }
+
+    #[test]
+    fn string_type_conversions() {
+        assert_type_conversion!(char, "string");
+
+        assert_type_conversion!(String, "string");
+        assert_type_conversion!(str, "string");
+    }
+
+    #[test]
+    fn collection_conversion() {
+        assert_type_conversion!([String], "string list");
+        assert_type_conversion!(Vec<String>, "string list");
+        assert_type_conversion!(HashMap<String, String>, "Map<string, string>");
+        assert_type_conversion!(HashSet<String>, "Set<string>");
+    }
+
+    #[test]
+    fn monad_conversion() {
+        assert_type_conversion!(Option<String>, "string option");
+        assert_type_conversion!(Result<String, String>, "RsResult<string, string>");
+    }
+
+    #[test]
+    fn reference_conversion() {
+        assert_type_conversion!(&str, "string");
+    }
+
+    #[test]
+    fn tuple_conversion() {
+        assert_type_conversion!((i32, String), "int32 * string")
+    }
+
+    #[test]
+    fn custom_type_conversion() {
+        assert_type_conversion!(MyCustomType, "MyCustomType")
+    }
+
+    // This is tested implicitly in other tests but it's ok to be explicit, plus this
+    // one tests structs with multiple fields
+    #[test]
+    fn struct_conversion() {
+        let tokens = quote!(
+            struct Test {
+                i: i32,
+                s: String,
+            }
+        );
+        assert_conversion!(tokens, "type Test ={\n    i: int32;\n    s: string; }")
+    }
+
+    #[test]
+    fn optional_fields() {
+        let tokens = quote!(
+            struct Patch {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                name: Option<String>,
+                #[serde(default)]
+                count: u32,
+                #[serde(default = "default_tags", skip_serializing_if = "Vec::is_empty")]
+                tags: Vec<String>,
+                id: u64,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Patch ={\n    name: string option;\n    count: uint32 option;\n    tags: string list option;\n    id: uint64; }"
+        );
+
+        let tokens = quote!(
+            #[serde(default)]
+            struct Settings {
+                verbose: bool,
+                #[serde(skip_deserializing)]
+                version: u32,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Settings ={\n    verbose: bool option;\n    version: uint32; }"
+        );
+    }
+
+    #[test]
+    fn flatten_markers() {
+        let tokens = quote!(
+            struct Doc {
+                title: String,
+                #[serde(flatten)]
+                meta: Meta,
+                #[serde(flatten)]
+                extra: HashMap<String, i64>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Doc ={\n    title: string;\n    (* ♒︎ flatten(Meta) *)\n    /// all other fields (`#[serde(flatten)]`): FSharp.Json doesn't collect these\n    extra: Map<string, int64> option; }"
+        );
+
+        let tokens = quote!(
+            struct Meta {
+                created: u64,
+                #[serde(flatten)]
+                more: More,
+            }
+        );
+        let parsed = FSharpify::new(tokens).parse().unwrap();
+        let fields = parsed.export_fields_source().unwrap();
+        assert_eq!(
+            without_notes(&fields),
+            "created: uint64;\n(* ♒︎ flatten(More) *)"
+        );
+        // the fields of `More` (and the types they refer to) are part of `Meta`
+        assert_eq!(parsed.definition.references, vec!["More"]);
+    }
+
+    #[test]
+    fn references() {
+        let tokens = quote!(
+            enum Expr {
+                Lit(i32),
+                Block { stmts: Vec<Stmt> },
+                Neg(Box<Expr>),
+                Call(HashMap<String, Option<Arg<Value>>>),
+            }
+        );
+        let parsed = FSharpify::new(tokens).parse().unwrap();
+        assert_eq!(
+            parsed.definition.references,
+            vec!["Arg", "Expr", "Stmt", "Value"]
+        );
+        // the types the derive checks have a definition
+        let types = parsed
+            .referenced_types
+            .iter()
+            .map(|ty| quote!(#ty).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec!["Stmt", "Expr", "Value", "Arg < Value >"]);
+    }
+
+    #[test]
+    fn type_model() {
+        use crate::model::{CaseFields, Primitive, Tagging, TypeBody, TypeRef};
+        let tokens = quote!(
+            #[serde(tag = "type", content = "data")]
+            enum Event {
+                Moved {
+                    #[serde(rename = "to")]
+                    to_: (i32, i32),
+                },
+                Batch(Vec<(String, Option<Event>)>),
+            }
+        );
+        let parsed = FSharpify::new(tokens).parse().unwrap();
+        let union = match parsed.definition.body {
+            TypeBody::Union(ref union) => union,
+            ref body => panic!("expected a union, got {:?}", body),
+        };
+        assert_eq!(
+            union.tagging,
+            Tagging::Adjacent {
+                tag: "type".to_string(),
+                content: "data".to_string()
+            }
+        );
+        let int32 = TypeRef::Primitive(Primitive::Int32);
+        match union.cases[0].fields {
+            CaseFields::Struct(ref fields) => {
+                assert_eq!(fields[0].serde_name, "to");
+                assert_eq!(fields[0].ty, TypeRef::Tuple(vec![int32.clone(), int32]));
+            }
+            ref fields => panic!("expected a struct variant, got {:?}", fields),
+        }
+        assert_eq!(parsed.definition.references, vec!["Event"]);
+        assert_eq!(
+            serde_json::to_value(&union.cases[1].fields).unwrap(),
+            serde_json::json!({
+                "style": "newtype",
+                "fields": { "kind": "list", "of": { "kind": "tuple", "of": [
+                    { "kind": "primitive", "of": "string" },
+                    { "kind": "option", "of": { "kind": "named", "of": { "name": "Event", "args": [] } } },
+                ] } },
+            })
+        );
+        // tuples are parenthesized where they are operands
+        assert_conversion!(
+            quote!(
+                struct Pairs(Vec<(u8, String)>, Option<(bool, bool)>);
+            ),
+            "type Pairs =(byte * string) list * (bool * bool) option"
+        );
+    }
+
+    #[test]
+    fn reserved_words_are_quoted() {
+        let tokens = quote!(
+            struct Test {
+                r#type: i64,
+                val: i64,
+                #[serde(rename = "end")]
+                end_: i64,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Test ={\n    ``type``: int64;\n    ``val``: int64;\n    [<JsonField(\"end\")>]\n    end_: int64; }"
+        );
+
+        let tokens = quote!(
+            enum Test {
+                #[allow(non_camel_case_types)]
+                module,
+                With(r#member),
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type TestWith = ``member``\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>]\ntype Test =\n  | [<JsonUnionCase(\"module\")>] Module\n  | With of TestWith"
+        );
+    }
+
+    #[test]
+    fn fs_idents() {
+        use crate::fs_ident::{fs_case_ident, fs_ident};
+        assert_eq!(fs_ident("content_type"), "content_type");
+        assert_eq!(fs_ident("r#namespace"), "``namespace``");
+        assert_eq!(fs_ident("content-type"), "``content-type``");
+        assert_eq!(fs_ident("1st"), "``1st``");
+        assert_eq!(fs_ident("_"), "``_``");
+        assert_eq!(fs_case_ident("red"), "Red");
+        assert_eq!(fs_case_ident("Type"), "Type");
+        assert_eq!(fs_case_ident("_Red"), "``_Red``");
+    }
+
+    #[test]
+    fn field_labels_map_to_serde_names() {
+        let tokens = quote!(
+            #[serde(rename_all = "kebab-case")]
+            struct Headers {
+                content_type: String,
+                #[serde(rename = "X")]
+                x: i64,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Headers ={\n    [<JsonField(\"content-type\")>]\n    content_type: string;\n    [<JsonField(\"X\")>]\n    x: int64; }"
+        );
+
+        let tokens = quote!(
+            #[serde(rename_all = "camelCase")]
+            #[fs(rename_all = "camelCase")]
+            struct Camel {
+                content_type: String,
+                #[serde(rename = "X")]
+                x: i64,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Camel ={\n    contentType: string;\n    [<JsonField(\"X\")>]\n    x: int64; }"
+        );
+
+        let tokens = quote!(
+            #[fs(rename_all = "PascalCase")]
+            struct Pascal {
+                content_type: String,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Pascal ={\n    [<JsonField(\"content_type\")>]\n    ContentType: string; }"
+        );
+    }
+
+    #[test]
+    fn newtype_conversion() {
+        let tokens = quote!(
+            struct Newtype(pub i64);
+        );
+        assert_conversion!(tokens, "type Newtype =int64")
+    }
+
+    #[test]
+    fn empty_struct_conversion() {
+        assert_conversion!(
+            quote!(
+                struct Unit;
+            ),
+            "type Unit =unit"
+        );
+        assert_conversion!(
+            quote!(
+                struct Skipped {
+                    #[serde(skip)]
+                    a: i32,
+                }
+            ),
+            "type Skipped =Map<string, unit>"
+        );
+        assert_conversion!(
+            quote!(
+                struct SkippedTuple(#[serde(skip)] i32, #[serde(skip)] i32);
+            ),
+            "type SkippedTuple =unit list"
+        );
+    }
+
+    #[test]
+    fn simple_enum_is_converted() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum SimpleEnum {
+                Foo,
+                Bar,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype SimpleEnum =\n  | Foo\n  | Bar"
+        )
+    }
+
+    #[test]
+    fn enum_with_struct_variants_is_converted() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum ComplexEnum {
+                Foo(String),
+                Bar { Baz: i32 },
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type ComplexEnumFoo = string\ntype ComplexEnumBar = {\n    Baz: int32;\n  }\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype ComplexEnum =\n  | Foo of ComplexEnumFoo\n  | Bar of ComplexEnumBar"
+        )
+    }
+
+    #[test]
+    fn internally_tagged_enum_is_converted() {
+        let tokens = quote!(
+            #[serde(tag = "type")]
+            enum Message {
+                Request { id: u32, method: String },
+                Response(Response),
+                Ping,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            r#"type MessageRequest = {
+    id: uint32;
+    method: string;
+  }
+type MessageResponse = Response
+[<JsonUnion(Mode = UnionMode.CaseKeyDiscriminatorField, CaseKeyField="type")>]
+type Message =
+  | Request of MessageRequest
+  | Response of MessageResponse
+  | Ping
+module Message =
+  /// decode an internally tagged `Message` by its "type" field
+  let decode (json: string) : Result<Message, string> =
+    try
+      match (Json.deserialize<{| ``type``: string |}> json).``type`` with
+      | "Request" -> Ok (Request (Json.deserialize<MessageRequest> json))
+      | "Response" -> Ok (Response (Json.deserialize<MessageResponse> json))
+      | "Ping" -> Ok Ping
+      | other -> Error (sprintf "unknown variant of Message: %s" other)
+    with e -> Error e.Message
+  /// encode an internally tagged `Message`, adding the "type" field to the variant's content
+  let encode (value: Message) : string =
+    let tagged (opening: string) (content: string) =
+      match content.Trim().Substring(1).TrimStart() with
+      | fields when fields.StartsWith "}" -> opening + "}"
+      | fields -> opening + "," + fields
+    match value with
+    | Request v -> tagged "{\"type\":\"Request\"" (Json.serialize v)
+    | Response v -> tagged "{\"type\":\"Response\"" (Json.serialize v)
+    | Ping -> "{\"type\":\"Ping\"" + "}""#
+        );
+        // FSharp.Json tags cases holding a single record itself
+        assert_conversion!(
+            quote!(
+                #[serde(tag = "type")]
+                enum Message {
+                    Request { id: u32 },
+                    Response(Response),
+                }
+            ),
+            "type MessageRequest = {\n    id: uint32;\n  }\ntype MessageResponse = Response\n[<JsonUnion(Mode = UnionMode.CaseKeyDiscriminatorField, CaseKeyField=\"type\")>]\ntype Message =\n  | Request of MessageRequest\n  | Response of MessageResponse"
+        )
+    }
+
+    #[test]
+    fn internally_tagged_newtypes_must_hold_objects() {
+        let tokens = quote!(
+            #[serde(tag = "type")]
+            enum Message {
+                Data(HashMap<String, i32>),
+                Count(u32),
+                Items(Vec<String>),
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].to_string(),
+            "Message: the newtype variants of an internally tagged enum must hold a struct or a map"
+        );
+    }
+
+    #[test]
+    fn externally_tagged_enum_is_converted() {
+        let tokens = quote!(
+            enum Shape {
+                Circle { radius: f64 },
+                Square(f64),
+                Empty,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type ShapeCircle = {\n    radius: float;\n  }\ntype ShapeSquare = float\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>]\ntype Shape =\n  | Circle of ShapeCircle\n  | Square of ShapeSquare\n  | Empty"
+        )
+    }
+
+    #[test]
+    fn untagged_enum_has_a_decoder() {
+        let tokens = quote!(
+            #[serde(untagged)]
+            enum Value {
+                Number(f64),
+                Text(String),
+                Nothing,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            r#"type ValueNumber = float
+type ValueText = string
+type Value =
+  | Number of ValueNumber
+  | Text of ValueText
+  | Nothing
+module Value =
+  /// decode an untagged `Value`, trying each variant in declaration order
+  let decode (json: string) : Result<Value, string> =
+    let attempts : (string * (unit -> Value)) list =
+      [ "Number", (fun () -> Number (Json.deserialize<ValueNumber> json))
+        "Text", (fun () -> Text (Json.deserialize<ValueText> json))
+        "Nothing", (fun () -> if json.Trim() = "null" then Nothing else failwith "expected null") ]
+    let rec attempt errors attempts =
+      match attempts with
+      | [] -> Error (sprintf "data did not match any variant of untagged enum Value: %s" (String.concat "; " (List.rev errors)))
+      | (name, decoder) :: rest ->
+        try Ok (decoder ())
+        with e -> attempt (sprintf "%s: %s" name e.Message :: errors) rest
+    attempt [] attempts
+  /// encode an untagged `Value` as just the variant's content
+  let encode (value: Value) : string =
+    match value with
+    | Number v -> Json.serialize v
+    | Text v -> Json.serialize v
+    | Nothing -> "null""#
+        )
+    }
+
+    #[test]
+    fn enum_with_complex_inner_types_is_converted() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum ComplexEnum {
+                Foo(Vec<Bar>),
+                Bar(Option<Baz>),
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type ComplexEnumFoo = Bar list\ntype ComplexEnumBar = Baz option\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype ComplexEnum =\n  | Foo of ComplexEnumFoo\n  | Bar of ComplexEnumBar"
+        )
+    }
+
+    #[test]
+    fn enum_handler() {
+        let tokens = quote!(
+            #[serde(tag = "type", content = "data")]
+            #[fs(handler_name = "MessageHandler", handler_return = "bool")]
+            enum FrontendMessage {
+                Open { path: String },
+                Resize(u32, u32),
+                Quit,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            r#"type FrontendMessageOpen = {
+    path: string;
+  }
+type FrontendMessageResize = uint32 * uint32
+[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField="type", CaseValueField="data")>]
+type FrontendMessage =
+  | Open of FrontendMessageOpen
+  | Resize of FrontendMessageResize
+  | Quit
+/// a function for each variant of `FrontendMessage`
+type MessageHandler =
+  { Open: FrontendMessageOpen -> bool
+    Resize: FrontendMessageResize -> bool
+    Quit: unit -> bool }
+module MessageHandler =
+  /// call the `handler` function for the variant of `value`
+  let dispatch (handler: MessageHandler) (value: FrontendMessage) : bool =
+    match value with
+    | Open v -> handler.Open v
+    | Resize v -> handler.Resize v
+    | Quit -> handler.Quit ()"#
+        );
+
+        let tokens = quote!(
+            #[fs(handler_return = "Async<unit>")]
+            enum Wrapped<T> {
+                Value(T),
+            }
+        );
+        let source = FSharpify::new(tokens)
+            .parse()
+            .unwrap()
+            .export_type_definition_source()
+            .finish();
+        assert!(source
+            .contains("type WrappedHandler<'T> =\n  { Value: WrappedValue<'T> -> Async<unit> }"));
+        assert!(source.contains(
+            "let dispatch<'T> (handler: WrappedHandler<'T>) (value: Wrapped<'T>) : Async<unit> ="
+        ));
+    }
+
+    #[test]
+    fn enum_factory() {
+        let tokens = quote!(
+            #[fs(factory_name = "Messages")]
+            enum Message {
+                /// open a file
+                Open {
+                    path: String,
+                    #[serde(rename = "readOnly")]
+                    read_only: bool,
+                },
+                Resize(u32, u32),
+                Focus(Box<Message>),
+                Empty(),
+                Quit,
+            }
+        );
+        let source = FSharpify::new(tokens)
+            .parse()
+            .unwrap()
+            .export_type_definition_source()
+            .finish();
+        let module = source.split("\nmodule ").nth(1).unwrap();
+        assert_eq!(
+            module.trim_end(),
+            r#"Messages =
+  /// open a file
+  let ``open`` (path: string) (read_only: bool) : Message = Open ({ path = path; read_only = read_only } : MessageOpen)
+  let resize (item1: uint32) (item2: uint32) : Message = Resize (item1, item2)
+  let focus (value: MessageFocus) : Message = Focus value
+  let empty () : Message = Empty []
+  let quit () : Message = Quit"#
+        );
+
+        let tokens = quote!(
+            #[fs(factory_return_name = "Msg<'T>")]
+            enum Wrapped<T> {
+                Value(T),
+            }
+        );
+        let source = FSharpify::new(tokens)
+            .parse()
+            .unwrap()
+            .export_type_definition_source()
+            .finish();
+        assert!(source.contains(
+            "module WrappedFactory =\n  let value<'T> (value: WrappedValue<'T>) : Msg<'T> = Value value"
+        ));
+    }
+
+    #[test]
+    fn enum_union_fields() {
+        let tokens = quote!(
+            #[fs(union_fields = "inline", handler_name = "ShapeHandler")]
+            enum Shape {
+                Rect {
+                    #[serde(rename = "w")]
+                    width: f64,
+                    height: Option<f64>,
+                },
+                Point(i32, i32),
+                Tag(String),
+                None,
+            }
+        );
+        let source = FSharpify::new(tokens)
+            .parse()
+            .unwrap()
+            .export_type_definition_source()
+            .finish();
+        let source = without_notes(&source);
+        assert!(source.contains(
+            "type Shape =\n  | Rect of width: float * height: float option\n  | Point of (int32 * int32)\n  | Tag of string\n  | None\n"
+        ));
+        assert!(!source.contains("type ShapeRect"));
+        assert!(source.contains("  { Rect: float -> float option -> unit\n"));
+        assert!(source.contains("    | Rect (f1, f2) -> handler.Rect f1 f2\n"));
+        // FSharp.Json writes inline fields as an array, serde as an object
+        assert!(source.contains(
+            r#"
+module Shape =
+  /// decode an externally tagged `Shape`: a unit variant's name or `{ "Variant": content }`
+  let decode (json: string) : Result<Shape, string> =
+    try
+      use doc = System.Text.Json.JsonDocument.Parse json
+      let root = doc.RootElement
+      if root.ValueKind = System.Text.Json.JsonValueKind.String then
+        match root.GetString() with
+        | "None" -> Ok None
+        | other -> Error (sprintf "unknown variant of Shape: %s" other)
+      else
+        let case = root.EnumerateObject() |> Seq.exactlyOne
+        let content = case.Value.GetRawText()
+        match case.Name with
+        | "Rect" -> Ok (let v = Json.deserialize<{| w: float; height: float option |}> content in Rect (v.w, v.height))
+        | "Point" -> Ok (Point (Json.deserialize<(int32 * int32)> content))
+        | "Tag" -> Ok (Tag (Json.deserialize<string> content))
+        | other -> Error (sprintf "unknown variant of Shape: %s" other)
+    with e -> Error e.Message
+  /// encode an externally tagged `Shape` as serde does
+  let encode (value: Shape) : string =
+    match value with
+    | Rect (f1, f2) -> Json.serialize {| Rect = {| w = f1; height = f2 |} |}
+    | Point v -> Json.serialize {| Point = v |}
+    | Tag v -> Json.serialize {| Tag = v |}
+    | None -> "\"None\""
+"#
+        ), "{}", source);
+
+        // inline fields have no JSON shape next to a tag, or without one
+        for tagging in [
+            quote!(#[serde(tag = "t")]),
+            quote!(#[serde(tag = "t", content = "c")]),
+            quote!(#[serde(untagged)]),
+        ] {
+            let tokens = quote!(
+                #[fs(union_fields = "inline")]
+                #tagging
+                enum Shape {
+                    Rect { width: f64, height: f64 },
+                }
+            );
+            let errors = FSharpify::new(tokens)
+                .parse()
+                .err()
+                .expect("expecting errors");
+            assert_eq!(
+                errors[0].to_string(),
+                "Shape: #[fs(union_fields = \"inline\")] needs an externally tagged enum"
+            );
+        }
+
+        let tokens = quote!(
+            #[fs(union_fields = "anonymous", factory_name = "Shapes")]
+            enum Shape {
+                Rect {
+                    #[serde(rename = "w")]
+                    width: f64,
+                    height: f64,
+                },
+            }
+        );
+        let source = FSharpify::new(tokens)
+            .parse()
+            .unwrap()
+            .export_type_definition_source()
+            .finish();
+        assert!(source.contains("  | Rect of {| w: float; height: float |}\n"));
+        assert!(source.contains(
+            "  let rect (width: float) (height: float) : Shape = Rect {| w = width; height = height |}"
+        ));
+
+        let tokens = quote!(
+            #[fs(union_fields = "inline")]
+            enum Wrapper {
+                Extra {
+                    #[serde(flatten)]
+                    extra: Extra,
+                },
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "Wrapper::Extra: #[serde(flatten)] and base64 fields need #[fs(union_fields = \"alias\")]"
+        );
+
+        let tokens = quote!(
+            #[fs(union_fields = "wide")]
+            enum E {
+                A,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "union_fields: \"wide\" must be one of \"alias\", \"inline\" or \"anonymous\""
+        );
+    }
+
+    #[test]
+    fn doc_comments_in_fields() {
+        let tokens = quote!(
+            /// a well-documented struct
+            struct WellDocumented {
+                /// even the field is documented
+                well_documented: String,
+            }
+        );
+        assert_conversion!(tokens, "/// a well-documented struct\ntype WellDocumented ={\n    /// even the field is documented\n    well_documented: string; }");
+    }
+
+    #[test]
+    fn doc_c_like_enum_variant() {
+        let tokens = quote!(
+            enum CLikeEnum {
+                /// a well-documented variant
+                One,
+                /// another well-documented variant
+                /// this time with multiple lines
+                Other,
+            }
+        );
+        assert_conversion!(tokens, "[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>]\ntype CLikeEnum =\n  /// a well-documented variant\n  | One\n  /// another well-documented variant\n  /// this time with multiple lines\n  | Other");
+    }
+
+    #[test]
+    fn renamed_unit_variants_use_serde_names() {
+        let tokens = quote!(
+            #[serde(rename_all = "lowercase")]
+            enum Color {
+                Red,
+                #[serde(rename = "verdant")]
+                Green,
+                Blue,
+            }
+        );
+        assert_conversion!(tokens, "[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>]\ntype Color =\n  | [<JsonUnionCase(\"red\")>] Red\n  | [<JsonUnionCase(\"verdant\")>] Green\n  | [<JsonUnionCase(\"blue\")>] Blue");
+    }
+
+    #[test]
+    fn doc_tuple_enum_variant() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum TupleEnum {
+                /// a well-documented variant
+                One(u32, String),
+                /// another well-documented variant
+                /// this time with multiple lines
+                Other(usize, usize),
+            }
+        );
+        assert_conversion!(tokens, "/// a well-documented variant\ntype TupleEnumOne = uint32 * string\n/// another well-documented variant\n/// this time with multiple lines\ntype TupleEnumOther = uint64 * uint64\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype TupleEnum =\n  /// a well-documented variant\n  | One of TupleEnumOne\n  /// another well-documented variant\n  /// this time with multiple lines\n  | Other of TupleEnumOther");
+    }
+
+    #[test]
+    fn doc_unit_enum_variant() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum UnitEnum {
+                /// a well-documented variant
+                One(),
+                /// another well-documented variant
+                /// this time with multiple lines
+                Other(),
+            }
+        );
+        assert_conversion!(tokens, "/// a well-documented variant\ntype UnitEnumOne = unit list\n/// another well-documented variant\n/// this time with multiple lines\ntype UnitEnumOther = unit list\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype UnitEnum =\n  /// a well-documented variant\n  | One of UnitEnumOne\n  /// another well-documented variant\n  /// this time with multiple lines\n  | Other of UnitEnumOther");
+    }
+
+    #[test]
+    fn doc_newtype_enum_variant() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum NewtypeEnum {
+                /// a well-documented variant
+                One(u32),
+                /// another well-documented variant
+                /// this time with multiple lines
+                Other(String),
+            }
+        );
+        assert_conversion!(tokens, "/// a well-documented variant\ntype NewtypeEnumOne = uint32\n/// another well-documented variant\n/// this time with multiple lines\ntype NewtypeEnumOther = string\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype NewtypeEnum =\n  /// a well-documented variant\n  | One of NewtypeEnumOne\n  /// another well-documented variant\n  /// this time with multiple lines\n  | Other of NewtypeEnumOther");
+    }
+
+    #[test]
+    fn doc_struct_enum_variant() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum StructEnum {
+                /// a well-documented variant
+                One {
+                    /// fields in variants
+                    x: f32,
+                    /// can be documented, too.
+                    y: f32,
+                },
+                /// another well-documented variant
+                /// this time with multiple lines
+                Other { name: String },
+            }
+        );
+        assert_conversion!(tokens, "/// a well-documented variant\ntype StructEnumOne = {\n    /// fields in variants\n    x: float32;\n    /// can be documented, too.\n    y: float32;\n  }\n/// another well-documented variant\n/// this time with multiple lines\ntype StructEnumOther = {\n    name: string;\n  }\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype StructEnum =\n  /// a well-documented variant\n  | One of StructEnumOne\n  /// another well-documented variant\n  /// this time with multiple lines\n  | Other of StructEnumOther");
+    }
+
+    #[test]
+    fn fs_type_is_used_verbatim() {
+        let tokens = quote!(
+            struct Chrono {
+                #[fs(fs_type = "System.DateTimeOffset")]
+                local: DateTime<Local>,
+                #[fs(fs_type = "(byte * string) list")]
+                pairs: ArrayVec<[(u8, String); 4]>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Chrono ={\n    local: System.DateTimeOffset;\n    pairs: (byte * string) list; }"
+        );
+    }
+
+    #[test]
+    fn generic_struct_conversion() {
+        let tokens = quote!(
+            struct Value<'a, T: ToString, U>
+            where
+                U: Clone,
+            {
+                value: T,
+                borrowed: &'a str,
+                others: Vec<Value<'a, i32, U>>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Value<'T, 'U> ={\n    value: 'T;\n    borrowed: string;\n    others: Value<int32, 'U> list; }"
+        );
+    }
+
+    #[test]
+    fn generic_enum_conversion() {
+        let tokens = quote!(
+            #[serde(tag = "t", content = "c")]
+            enum Either<L, R> {
+                Left(L),
+                Right { value: R },
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type EitherLeft<'L> = 'L\ntype EitherRight<'R> = {\n    value: 'R;\n  }\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldValue, CaseKeyField=\"t\", CaseValueField=\"c\")>]\ntype Either<'L, 'R> =\n  | Left of EitherLeft<'L>\n  | Right of EitherRight<'R>"
+        );
+
+        // an abbreviation must use all its type parameters
+        let tokens = quote!(
+            struct Id<T>(u64, #[serde(skip)] PhantomData<T>);
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "Id: the FSharp type abbreviation doesn't use the type parameter(s) 'T, which FSharp rejects; \
+             remove them or make this a struct with named fields"
+        );
+        let source = FSharpify::new(quote!(
+            enum E<T> {
+                A(#[fs(fs_type = "'T list")] Vec<T>),
+                B(u8),
+            }
+        ))
+        .parse()
+        .unwrap()
+        .export_type_definition_source()
+        .finish();
+        let source = without_notes(&source);
+        assert!(
+            source.contains("type EA<'T> = 'T list\ntype EB = byte\n"),
+            "{}",
+            source
+        );
+        assert!(
+            source.contains("  | A of EA<'T>\n  | B of EB"),
+            "{}",
+            source
+        );
+    }
+
+    #[test]
+    fn byte_buffer_conversion() {
+        let tokens = quote!(
+            struct Buffers {
+                #[serde(serialize_with = "fsharp_definitions::as_byte_string")]
+                byte_string: Vec<u8>,
+                #[serde(with = "serde_bytes")]
+                serde_bytes: Option<Vec<u8>>,
+                byte_buf: serde_bytes::ByteBuf,
+                bytes: bytes::Bytes,
+                bytes_mut: bytes::BytesMut,
+                #[serde(with = "serde_bytes")]
+                imported: ByteBuf,
+                #[serde(with = "base64")]
+                base64: Vec<u8>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type Buffers ={\n    byte_string: string;\n    serde_bytes: byte[] option;\n    byte_buf: byte[];\n    bytes: byte[];\n    bytes_mut: byte[];\n    imported: byte[];\n    [<JsonField(Transform=typeof<Base64Bytes>)>]\n    base64: byte[]; }"
+        );
+
+        // only the known paths: these are other types, or other serializers
+        let tokens = quote!(
+            struct NotBuffers {
+                bytes: Bytes,
+                #[serde(with = "hex_base64_len")]
+                len: Vec<u8>,
+                #[serde(with = "my::serde_bytes_hex")]
+                hex: Vec<u8>,
+            }
+        );
+        assert_conversion!(
+            tokens,
+            "type NotBuffers ={\n    bytes: Bytes;\n    len: byte list;\n    hex: byte list; }"
+        );
+
+        let tokens = quote!(
+            struct B64 {
+                #[serde(serialize_with = "as_base64")]
+                data: &'static [u8],
+            }
+        );
+        let parsed = FSharpify::new(tokens).parse().unwrap();
+        assert_eq!(
+            parsed.helpers.iter().map(|h| h.name()).collect::<Vec<_>>(),
+            vec!["Base64Bytes"]
+        );
+    }
+
+    #[test]
+    fn fs_type_check_accepts_fsharp_types() {
+        use crate::fs_type::fs_type_check;
+        for ty in &[
+            "string",
+            "int64 list option",
+            "'T list",
+            "Map<string, Set<int>>",
+            "(int * string) list",
+            "byte[]",
+            "float[,]",
+            "int -> string -> unit",
+            "{| a: int; b: string option |}",
+            "``weird type``",
+        ] {
+            assert_eq!(fs_type_check(ty), Ok(()), "{}", ty);
+        }
+        for ty in &["", "int list)", "string[]]", "{| a |}", "int * ", "a.b."] {
+            assert!(fs_type_check(ty).is_err(), "{}", ty);
+        }
+    }
+
+    // Error tests
+
+    #[test]
+    fn flatten_generic() {
+        let tokens = quote!(
+            struct S<T> {
+                a: i32,
+                #[serde(flatten)]
+                b: Meta<T>,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "S: #[serde(flatten)] only works for maps and structs without generics."
+        );
+    }
+
+    #[test]
+    fn colliding_labels() {
+        let tokens = quote!(
+            #[fs(rename_all = "camelCase")]
+            struct S {
+                content_type: i32,
+                contentType: i32,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "fsharp record label `contentType` is used more than once in `S`"
+        );
+
+        let tokens = quote!(
+            enum E {
+                red,
+                Red,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "fsharp union case `Red` is used more than once in `E`"
+        );
+    }
+
+    #[test]
+    fn bad_rename_all() {
+        let tokens = quote!(
+            #[fs(rename_all = "kebab-case")]
+            struct S {
+                a: i32,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "rename_all: \"kebab-case\" must be one of \"camelCase\", \"PascalCase\" or \"snake_case\""
+        );
+    }
+
+    #[test]
+    fn bad_handler_or_factory() {
+        let tokens = quote!(
+            #[fs(handler_name = "PointHandler")]
+            struct Point {
+                x: i32,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "Point: struct cannot have a handler or factory"
+        );
+
+        let tokens = quote!(
+            #[fs(handler_return = "Result<unit")]
+            enum E {
+                A,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "handler_return: \"Result<unit\" is not a valid fsharp type: expected `>` found end of type"
+        );
+
+        let tokens = quote!(
+            #[fs(factory_name = "Make")]
+            enum E {
+                A {
+                    #[serde(flatten)]
+                    meta: Meta,
+                },
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        assert_eq!(
+            errors[0].to_string(),
+            "E: a factory can't construct variants with #[serde(flatten)] structs"
+        );
+    }
+
+    #[test]
+    fn bad_fs_type() {
+        let tokens = quote!(
+            struct S {
+                #[fs(fs_type = "number | null")]
+                a: i32,
+                #[fs(fs_type = "Map<string, int")]
+                b: HashMap<String, i32>,
+            }
+        );
+        let errors = FSharpify::new(tokens)
+            .parse()
+            .err()
+            .expect("expecting errors");
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "fs_type: \"number | null\" is not a valid fsharp type: unexpected character `|`",
+                "fs_type: \"Map<string, int\" is not a valid fsharp type: expected `>` found end of type",
+            ]
+        );
+    }
+
+    #[test]
+    fn conversion_is_only_valid_for_structs_or_enums() {
+        let tokens = quote!(type Foo(String));
+
+        let result = std::panic::catch_unwind(move || FSharpify::new(tokens).parse());
+        match result {
+            Ok(_) => panic!("expecting panic!"),
+            Err(ref msg) => assert!(msg
+                .downcast_ref::<String>()
+                .unwrap()
+                .contains("expected one of: `struct`, `enum`, `union`")),
+        }
+    }
+
+    // TODO: None of these compiled at all previously, nevermind passing, and it's less bang for buck fixing it than getting the type conversion test coverage.
+    // We should probably bring them back in future though.
+
+    // #[test]
+    // fn tag_clash_in_enum() {
+    //     let tokens = quote!(
+    //         #[derive(Serialize)]
+    //         #[serde(tag = "kind")]
+    //         enum A {
+    //             Unit,
+    //             B { kind: i32, b: String },
+    //         }
+    //     );
+
+    //     let result = std::panic::catch_unwind(move || FSharpify::new(tokens).parse());
+    //     match result {
+    //         Ok(_x) => assert!(false, "expecting panic!"),
+    //         Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
+    //         @r###"2 errors:
+    // # variant field name `kind` conflicts with internal tag
+    // # clash with field in "A::B". Maybe use a #[serde(content="...")] attribute."###
+    //         ),
+    //     }
+    // }
+    // #[test]
+    // fn verify_is_recognized() {
+    //     let tokens = quote!(
+    //         #[derive(Serialize)]
+    //         #[fs(guard = "blah")]
+    //         struct S {
+    //             a: i32,
+    //             b: f64,
+    //         }
+    //     );
+    //     let result = std::panic::catch_unwind(move || FSharpify::new(tokens).parse_verify());
+    //     match result {
+    //         Ok(_x) => assert!(false, "expecting panic!"),
+    //         Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
+    //         @r###"S: guard must be true or false not ""blah"""###
+    //         ),
+    //     }
+    // }
+    // #[test]
+    // fn turbofish() {
+    //     let tokens = quote!(
+    //         #[derive(FSharpify)]
+    //         #[fs(turbofish = "<i32>")]
+    //         struct S<T> {
+    //             a: i32,
+    //             b: Vec<T>,
+    //         }
+    //     );
+    //     let ty = FSharpify::parse(true, tokens);
+    //     let i = &ty.ctxt.ident;
+    //     let g = ty.ctxt.global_attrs.turbofish.unwrap_or_else(|| quote!());
+    //     let res = quote!(#i#g::fsharp_ify()).to_string();
+    //     assert_snapshot_matches!(res,
+    //     @"S < i32 > :: fsharp_ify ( )" );
+    // }
+    // #[test]
+    // fn bad_fs_as() {
+    //     let tokens = quote!(
+    //         #[derive(FSharpify)]
+
+    //         struct S<T> {
+    //             #[fs(ts_as = "😀i32>")]
+    //             a: i32,
+    //             #[fs(ts_as = "T[]")]
+    //             b: Vec<T>,
+    //         }
+    //     );
+    //     let result = std::panic::catch_unwind(move || FSharpify::new(tokens).parse(true));
+    //     match result {
+    //         Ok(_x) => assert!(false, "expecting panic!"),
+    //         Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
+    //         @r###"2 errors:
+    // # ts_as: "😀i32>" is not a valid rust type
+    // # ts_as: "T[]" is not a valid rust type"###
+    //         ),
+    //     }
+    // }
+}
@@ -0,0 +1,225 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::{bytes_kind, last_path_element, BytesKind, FSType, FieldContext};
+use crate::model::{Primitive, TypeRef};
+use crate::render::fs_type;
+
+fn p(v: Primitive) -> TypeRef {
+    TypeRef::Primitive(v)
+}
+fn todo(v: &str) -> TypeRef {
+    TypeRef::Unsupported(v.to_string())
+}
+fn boxed(ty: TypeRef) -> Box<TypeRef> {
+    Box::new(ty)
+}
+
+impl<'a> FieldContext<'a> {
+    #[allow(clippy::cognitive_complexity)]
+    fn generic_to_fs(&self, fs: &FSType) -> TypeRef {
+        let to_fs = |ty: &syn::Type| self.type_to_fs(ty);
+        let name = fs.ident.to_string();
+        let widen = self.attrs.widen_numbers || self.ctxt.global_attrs.widen_numbers;
+        match name.as_ref() {
+            // #[fs(widen_numbers)] keeps the old "everything is an int64 or a float" behaviour
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+            | "i128" | "isize"
+                if widen =>
+            {
+                p(Primitive::Int64)
+            }
+            "f64" | "f32" if widen => p(Primitive::Float),
+            "u8" => p(Primitive::Byte),
+            "i8" => p(Primitive::SByte),
+            "u16" => p(Primitive::UInt16),
+            "i16" => p(Primitive::Int16),
+            "u32" => p(Primitive::UInt32),
+            "i32" => p(Primitive::Int32),
+            "u64" => p(Primitive::UInt64),
+            "i64" => p(Primitive::Int64),
+            "u128" | "i128" => p(Primitive::BigInt),
+            // nativeint/unativeint are platform dependent and FSharp.Json can't decode them
+            "usize" => p(Primitive::UInt64),
+            "isize" => p(Primitive::Int64),
+            "f32" => p(Primitive::Float32),
+            "f64" => p(Primitive::Float),
+            "String" | "str" | "char" | "Path" | "PathBuf" => p(Primitive::String),
+            "bool" => p(Primitive::Bool),
+            "Box" | "Cow" | "Rc" | "Arc" | "Cell" | "RefCell" if fs.args.len() == 1 => {
+                to_fs(&fs.args[0])
+            }
+            "Duration" => TypeRef::Struct(vec![
+                ("secs".to_string(), p(Primitive::Int64)),
+                ("nanos".to_string(), p(Primitive::Int64)),
+            ]),
+            "SystemTime" => TypeRef::Struct(vec![
+                ("secs_since_epoch".to_string(), p(Primitive::Int64)),
+                ("nanos_since_epoch".to_string(), p(Primitive::Int64)),
+            ]),
+            // std::collections
+            "Vec" | "VecDeque" | "LinkedList" if fs.args.len() == 1 => {
+                self.type_to_array(&fs.args[0])
+            }
+            "HashMap" | "BTreeMap" if fs.args.len() == 2 => {
+                TypeRef::map(to_fs(&fs.args[0]), to_fs(&fs.args[1]))
+            }
+            "HashSet" | "BTreeSet" if fs.args.len() == 1 => TypeRef::Set(boxed(to_fs(&fs.args[0]))),
+            "Option" if fs.args.len() == 1 => TypeRef::option(to_fs(&fs.args[0])),
+            "Result" if fs.args.len() == 2 => {
+                TypeRef::Result(boxed(to_fs(&fs.args[0])), boxed(to_fs(&fs.args[1])))
+            }
+            // "Fn" | "FnOnce" | "FnMut" => {
+            //     let args = self.derive_syn_types(&fs.args);
+            //     if let Some(ref rt) = fs.return_type {
+            //         let rt = to_fs(rt);
+            //         quote! { (#(#args),*) => #rt }
+            //     } else {
+            //         quote! { (#(#args),*) => undefined }
+            //     }
+            // }
+            name_str => {
+                let owned = fs.path();
+                let path: Vec<&str> = owned.iter().map(|s| s.as_ref()).collect();
+                let bytes = self.field.and_then(bytes_kind);
+                match path[..] {
+                    ["serde_json", "Value"] => p(Primitive::Obj),
+                    // serde_bytes::ByteBuf, bytes::Bytes serialize as byte arrays
+                    ["serde_bytes", "ByteBuf"] | ["bytes", "Bytes"] | ["bytes", "BytesMut"]
+                        if fs.args.is_empty() =>
+                    {
+                        match bytes {
+                            Some(BytesKind::ByteString) | Some(BytesKind::Base64) => {
+                                p(Primitive::String)
+                            }
+                            _ => TypeRef::Array(boxed(p(Primitive::Byte))),
+                        }
+                    }
+                    // imported ones need an explicit `#[serde(with = "serde_bytes")]`
+                    ["ByteBuf"] | ["Bytes"] | ["BytesMut"]
+                        if fs.args.is_empty() && bytes == Some(BytesKind::SerdeBytes) =>
+                    {
+                        TypeRef::Array(boxed(p(Primitive::Byte)))
+                    }
+                    ["chrono", "DateTime"] => p(Primitive::String),
+                    [_] if fs.args.is_empty() && self.ctxt.type_params.contains(&fs.ident) => {
+                        TypeRef::TypeParam(name_str.to_string())
+                    }
+                    _ => {
+                        // another (derived) type: the organizer orders definitions by these
+                        self.ctxt
+                            .references
+                            .borrow_mut()
+                            .insert(name_str.to_string());
+                        TypeRef::Named {
+                            name: name_str.to_string(),
+                            args: self.derive_syn_types(&fs.args).collect(),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn type_to_array(&self, elem: &syn::Type) -> TypeRef {
+        // check for [u8] or Vec<u8>
+
+        if let Some(ty) = self.get_path(elem) {
+            if ty.ident == "u8" {
+                match self.field.and_then(bytes_kind) {
+                    // `derive_field` turns a base64 `string` into a transformed `byte[]`
+                    Some(BytesKind::ByteString) | Some(BytesKind::Base64) => {
+                        return p(Primitive::String)
+                    }
+                    Some(BytesKind::SerdeBytes) => {
+                        return TypeRef::Array(boxed(p(Primitive::Byte)))
+                    }
+                    None => (),
+                }
+            };
+        };
+
+        TypeRef::list(self.type_to_fs(elem))
+    }
+    /// # convert a `syn::Type` rust type to a
+    /// `TypeRef` of the type model: basically i32 => int32 etc.
+    ///
+    /// field is the current Field for which we are trying a conversion
+    pub fn type_to_fs(&self, ty: &syn::Type) -> TypeRef {
+        // `type_to_fs` recursively calls itself occationally
+        // finding a Path which it hands to last_path_element
+        // which generates a "simplified" FSType struct which
+        // is handed to `generic_to_fs` which possibly "bottoms out"
+        // by generating the builtin types.
+
+        use syn::Type::*;
+        use syn::{
+            TypeArray, TypeBareFn, TypeGroup, TypeImplTrait, TypeParamBound, TypeParen, TypePath,
+            TypePtr, TypeReference, TypeSlice, TypeTraitObject, TypeTuple,
+        };
+        match ty {
+            Slice(TypeSlice { elem, .. })
+            | Array(TypeArray { elem, .. })
+            | Ptr(TypePtr { elem, .. }) => self.type_to_array(elem),
+            Reference(TypeReference { elem, .. }) => self.type_to_fs(elem),
+            // fn(a: A,b: B, c:C) -> D
+            BareFn(TypeBareFn { inputs, .. }) => {
+                self.ctxt
+                    .err_msg(inputs, "we do not support FSharpifying functions");
+                todo("obj bare fn") // any type?
+            }
+            Never(..) => todo("never?"),
+            Tuple(TypeTuple { elems, .. }) => {
+                TypeRef::Tuple(elems.iter().map(|t| self.type_to_fs(t)).collect())
+            }
+
+            Path(TypePath { path, qself }) => match last_path_element(path) {
+                Some(ref fs) => {
+                    let ty_ref = self.generic_to_fs(fs);
+                    // the derive checks these derive `FSharpDefinition` too
+                    // (`Box<Expr>` is `Expr`: that has been checked already)
+                    if qself.is_none()
+                        && matches!(ty_ref, TypeRef::Named { ref name, .. } if fs.ident == name)
+                    {
+                        self.ctxt.referenced_types.borrow_mut().push(ty.clone());
+                    }
+                    ty_ref
+                }
+                _ => todo("type path?"),
+            },
+            TraitObject(TypeTraitObject { bounds, .. })
+            | ImplTrait(TypeImplTrait { bounds, .. }) => {
+                let elems = bounds
+                    .iter()
+                    .filter_map(|t| match t {
+                        TypeParamBound::Trait(t) => last_path_element(&t.path),
+                        _ => None, // skip lifetime etc.
+                    })
+                    .map(|t| fs_type(&self.generic_to_fs(&t)))
+                    .collect::<Vec<String>>()
+                    .join(" + ");
+
+                // TODO check for zero length?
+                // A + B + C => A & B & C
+                todo(&format!("trait object {}", elems))
+            }
+            Paren(TypeParen { elem, .. }) | Group(TypeGroup { elem, .. }) => {
+                let tp = fs_type(&self.type_to_fs(elem));
+                todo(&format!("type paren {}", tp))
+            }
+            Infer(..) | Macro(..) | Verbatim(..) => todo("infer, macro, or verbatim?"),
+            _ => todo("other?"),
+        }
+    }
+
+    pub fn derive_syn_types(
+        &'a self,
+        types: &'a [syn::Type],
+    ) -> impl Iterator<Item = TypeRef> + 'a {
+        types.iter().map(move |ty| self.type_to_fs(ty))
+    }
+}
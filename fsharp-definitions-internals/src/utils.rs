@@ -15,24 +15,41 @@ pub fn ident_from_str(s: &str) -> Ident {
 pub fn field_type_name(ty: &syn::Type) -> Option<String> {
     use syn::Type::Path;
     match ty {
-        Path(syn::TypePath { path, .. }) => match path.segments.last() {
-            Some(t) => Some(t.ident.to_string()),
-            _ => None,
-        },
+        Path(syn::TypePath { path, .. }) => path.segments.last().map(|t| t.ident.to_string()),
         _ => None,
     }
 }
 
-pub fn is_bytes(field: &ast::Field<'_>) -> bool {
-    // check for #[serde(with="serde_bytes")]
+/// How a `[u8]` or `Vec<u8>` field is serialized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BytesKind {
+    /// `#[serde(serialize_with = "as_byte_string")]`: a `\xdd` encoded string
+    ByteString,
+    /// `#[serde(with = "serde_bytes")]`: serde_json writes an array of numbers
+    SerdeBytes,
+    /// `#[serde(with = "base64")]` or similar: a base64 encoded string
+    Base64,
+}
+
+pub fn bytes_kind(field: &ast::Field<'_>) -> Option<BytesKind> {
+    // check for #[serde(with="serde_bytes")] etc.
     use syn::ExprPath;
     if let Some(ExprPath { ref path, .. }) = field.attrs.serialize_with() {
-        match path.segments.last() {
-            Some(t) => return t.ident == "as_byte_string",
-            _ => return false,
-        }
+        let segments = path
+            .segments
+            .iter()
+            .map(|s| s.ident.to_string())
+            .collect::<Vec<_>>();
+        let segments = segments.iter().map(|s| s.as_ref()).collect::<Vec<&str>>();
+        return match segments[..] {
+            [.., "as_byte_string"] => Some(BytesKind::ByteString),
+            // `with = "serde_bytes"` serializes with `serde_bytes::serialize`
+            ["serde_bytes", "serialize"] => Some(BytesKind::SerdeBytes),
+            ["base64", "serialize"] | [.., "as_base64"] => Some(BytesKind::Base64),
+            _ => None,
+        };
     };
-    false
+    None
 }
 
 #[allow(unused)]
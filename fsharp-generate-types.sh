@@ -2,7 +2,7 @@
 
 # Requirements:
 #
-# Must have nodejs accessible via `node` executable in PATH
+# Must have cargo accessible in PATH
 #
 # Environment variables:
 #  - FSFY_MODELS_HEAD_FILE
@@ -14,6 +14,6 @@ cd $(dirname "${0}")
 SCRIPTPATH=$(pwd -L)
 cd -
 
+# fsharp-definitions-organize writes to $FSFY_OUT_FILE itself
 FSFY_SHOW_CODE=1 cargo check --quiet 2>&1 \
-  | node $SCRIPTPATH/fsharp-definitions-organize.js \
-  > $FSFY_OUT_FILE
+  | cargo run --quiet --manifest-path $SCRIPTPATH/Cargo.toml --bin fsharp-definitions-organize
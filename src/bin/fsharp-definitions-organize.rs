@@ -0,0 +1,122 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reads the output of `FSFY_SHOW_CODE=1 cargo check` and writes one FSharp file.
+//!
+//! ```sh
+//! FSFY_SHOW_CODE=1 cargo check 2>&1 | fsharp-definitions-organize --head head.fs --out Models.fs
+//! # or, with the sections written one file per type
+//! FSFY_OUT_DIR=target/fsfy cargo check && fsharp-definitions-organize target/fsfy > Models.fs
+//! ```
+
+use fsharp_definitions::organize::Organizer;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "\
+usage: fsharp-definitions-organize [--head FILE] [--out FILE] [--keep-notes] [INPUT]
+
+Collects the FSharp sections printed by `FSFY_SHOW_CODE=1 cargo check` from INPUT
+(or stdin) and writes them, after the header, in dependency order. If INPUT is
+a directory (`FSFY_OUT_DIR`) all the `.fs` files in it are read.
+
+  --head FILE    header to start with (default: $FSFY_MODELS_HEAD_FILE)
+  --out FILE     where to write (default: $FSFY_OUT_FILE, or stdout)
+  --keep-notes   keep the `// NOTE:` lines (default: $FSFY_KEEP_NOTES=1)";
+
+struct Args {
+    head: Option<String>,
+    out: Option<String>,
+    input: Option<String>,
+    keep_notes: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        head: env::var("FSFY_MODELS_HEAD_FILE").ok(),
+        out: env::var("FSFY_OUT_FILE").ok(),
+        input: None,
+        keep_notes: env::var("FSFY_KEEP_NOTES").as_deref() == Ok("1"),
+    };
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--head" => args.head = Some(argv.next().ok_or("--head needs a FILE")?),
+            "--out" => args.out = Some(argv.next().ok_or("--out needs a FILE")?),
+            "--keep-notes" => args.keep_notes = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if args.input.is_none() => args.input = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(args)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let input = match args.input {
+        Some(ref path) if Path::new(path).is_dir() => read_dir(Path::new(path))?,
+        Some(ref path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| format!("stdin: {}", e))?;
+            input
+        }
+    };
+    let head = match args.head {
+        Some(ref path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => String::new(),
+    };
+
+    let mut organizer = Organizer::new().keep_notes(args.keep_notes);
+    organizer.read(&input);
+    let source = organizer.finish(&head);
+    for warning in organizer.warnings() {
+        eprintln!("{}", warning);
+    }
+
+    match args.out {
+        Some(ref path) => fs::write(path, source).map_err(|e| format!("{}: {}", path, e)),
+        None => io::stdout()
+            .write_all(source.as_bytes())
+            .map_err(|e| format!("stdout: {}", e)),
+    }
+}
+
+/// the `.fs` files written to `FSFY_OUT_DIR`, in name order
+fn read_dir(dir: &Path) -> Result<String, String> {
+    let error = |e: io::Error| format!("{}: {}", dir.display(), e);
+    let mut paths = fs::read_dir(dir)
+        .map_err(error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(error)?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "fs"));
+    paths.sort();
+    let mut input = String::new();
+    for path in paths {
+        input.push_str(&fs::read_to_string(&path).map_err(error)?);
+        input.push('\n');
+    }
+    Ok(input)
+}
+
+fn main() {
+    if let Err(e) = parse_args().and_then(run) {
+        eprintln!("fsharp-definitions-organize: {}\n\n{}", e, USAGE);
+        process::exit(1);
+    }
+}
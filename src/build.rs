@@ -0,0 +1,291 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Generate the FSharp file from `build.rs` (or a test).
+//!
+//! Instead of scraping `FSFY_SHOW_CODE=1 cargo check` the [`Generator`] parses
+//! the rust source files itself, finds every `#[derive(FSharpDefinition)]`
+//! struct and enum and runs them through the same pipeline as the derive.
+//! The definitions are put together by the [`Organizer`](crate::organize::Organizer).
+//!
+//! ```no_run
+//! // in build.rs `main`
+//! fsharp_definitions::build::Generator::new()
+//!     .source("src")
+//!     .header("module Models\n")
+//!     .rerun_if_changed(true)
+//!     .write_to("fsharp/Models.fs")
+//!     .unwrap();
+//! ```
+
+use crate::model::TypeDefinition;
+use crate::organize::Organizer;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What went wrong generating the definitions.
+#[derive(Debug)]
+pub enum Error {
+    /// reading a source file or writing the output
+    Io(PathBuf, io::Error),
+    /// a source file isn't valid rust
+    Parse(PathBuf, syn::Error),
+    /// a derived type can't be turned into FSharp (the errors the derive would report)
+    Definition(PathBuf, Vec<syn::Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::Definition(path, errors) => {
+                let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                write!(f, "{}: {}", path.display(), errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Finds `#[derive(FSharpDefinition)]` types in rust sources and writes their FSharp definitions.
+#[derive(Debug, Default)]
+pub struct Generator {
+    sources: Vec<PathBuf>,
+    header: String,
+    keep_notes: bool,
+    rerun_if_changed: bool,
+}
+
+impl Generator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a rust file, or a directory which is searched for `.rs` files
+    pub fn source<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.sources.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// text (e.g. `module Models`) the output starts with
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = header.to_string();
+        self
+    }
+
+    /// keep the `// NOTE: ` lines the derive leaves to explain itself
+    pub fn keep_notes(mut self, keep_notes: bool) -> Self {
+        self.keep_notes = keep_notes;
+        self
+    }
+
+    /// print `cargo:rerun-if-changed` for every source file, for use in `build.rs`
+    pub fn rerun_if_changed(mut self, rerun_if_changed: bool) -> Self {
+        self.rerun_if_changed = rerun_if_changed;
+        self
+    }
+
+    /// the FSharp source for all the derived types
+    pub fn generate(&self) -> Result<String, Error> {
+        let mut organizer = Organizer::new().keep_notes(self.keep_notes);
+        for (path, input) in self.derived_inputs()? {
+            let sections = fsharp_definitions_internals::definition_sections(input)
+                .map_err(|errors| Error::Definition(path, errors))?;
+            organizer.read(&sections);
+        }
+        for warning in organizer.warnings() {
+            eprintln!("{}", warning);
+        }
+        Ok(organizer.finish(&self.header))
+    }
+
+    /// the [type model](crate::model) of all the derived types, in source order,
+    /// e.g. to serialize them for other tools
+    pub fn definitions(&self) -> Result<Vec<TypeDefinition>, Error> {
+        self.derived_inputs()?
+            .into_iter()
+            .map(|(path, input)| {
+                fsharp_definitions_internals::type_definition(input)
+                    .map_err(|errors| Error::Definition(path, errors))
+            })
+            .collect()
+    }
+
+    /// the derived structs and enums of all the source files
+    fn derived_inputs(&self) -> Result<Vec<(PathBuf, syn::DeriveInput)>, Error> {
+        let mut files = vec![];
+        for source in &self.sources {
+            rust_files(source, &mut files)?;
+        }
+        files.sort();
+
+        let mut derived = vec![];
+        for path in files {
+            if self.rerun_if_changed {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+            let code = fs::read_to_string(&path).map_err(|e| Error::Io(path.clone(), e))?;
+            let file = syn::parse_file(&code).map_err(|e| Error::Parse(path.clone(), e))?;
+            let mut inputs = vec![];
+            derived_items(&file.items, &mut inputs);
+            derived.extend(inputs.into_iter().map(|input| (path.clone(), input)));
+        }
+        Ok(derived)
+    }
+
+    /// generate and write the FSharp source to `path`, creating directories as needed
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let source = self.generate()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::Io(dir.to_path_buf(), e))?;
+        }
+        fs::write(path, source).map_err(|e| Error::Io(path.to_path_buf(), e))
+    }
+}
+
+fn rust_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if path.is_dir() {
+        let entries = fs::read_dir(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| Error::Io(path.to_path_buf(), e))?;
+            rust_files(&entry.path(), files)?;
+        }
+    } else if path.extension().is_some_and(|ext| ext == "rs") {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// `#[derive(..., FSharpDefinition)]` structs and enums, including those in inline modules
+fn derived_items(items: &[syn::Item], inputs: &mut Vec<syn::DeriveInput>) {
+    for item in items {
+        match item {
+            syn::Item::Struct(item) if derives_fsharp_definition(&item.attrs) => {
+                inputs.push(item.clone().into())
+            }
+            syn::Item::Enum(item) if derives_fsharp_definition(&item.attrs) => {
+                inputs.push(item.clone().into())
+            }
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => derived_items(items, inputs),
+            _ => (),
+        }
+    }
+}
+
+fn derives_fsharp_definition(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .any(|meta| match meta {
+            syn::Meta::List(list) => list.nested.iter().any(|nested| match nested {
+                syn::NestedMeta::Meta(meta) => meta
+                    .path()
+                    .segments
+                    .last()
+                    .is_some_and(|s| s.ident == "FSharpDefinition"),
+                _ => false,
+            }),
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_derived_types() {
+        let dir = std::env::temp_dir().join(format!("fsfy-generator-{}", std::process::id()));
+        fs::create_dir_all(dir.join("models")).unwrap();
+        fs::write(
+            dir.join("lib.rs"),
+            "#[derive(Serialize, FSharpDefinition)]\nstruct Line { from: Point, to: Point }\n\
+             #[derive(Serialize)]\nstruct Ignored { a: i32 }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("models").join("point.rs"),
+            "mod inner {\n  #[derive(fsharp_definitions::FSharpDefinition)]\n  pub struct Point { x: i32, y: i32 }\n}\n",
+        )
+        .unwrap();
+
+        let generator = Generator::new().source(&dir).header("module Models");
+        let source = generator.generate();
+        let names = generator
+            .definitions()
+            .unwrap()
+            .into_iter()
+            .map(|def| def.name)
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(names, vec!["Line", "Point"]);
+        assert_eq!(
+            source.unwrap(),
+            "module Models\ntype Point ={\n    x: int32;\n    y: int32; }\n\ntype Line ={\n    from: Point;\n    ``to``: Point; }\n"
+        );
+    }
+
+    #[test]
+    fn flattened_types_come_first() {
+        let dir =
+            std::env::temp_dir().join(format!("fsfy-generator-flatten-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("lib.rs"),
+            "#[derive(Serialize, FSharpDefinition)]\nenum A { V { #[serde(flatten)] meta: M, y: i32 } }\n\
+             #[derive(Serialize, FSharpDefinition)]\nstruct M { z: Z }\n\
+             #[derive(Serialize, FSharpDefinition)]\nstruct Z(String);\n",
+        )
+        .unwrap();
+
+        let source = Generator::new().source(&dir).generate();
+        fs::remove_dir_all(&dir).unwrap();
+        let source = source.unwrap();
+        let position = |def: &str| source.find(def).unwrap_or_else(|| panic!("{}", source));
+        assert!(position("type Z =") < position("type AV ="), "{}", source);
+        assert!(source.contains("    z: Z;\n    y: int32;\n"), "{}", source);
+    }
+
+    #[test]
+    fn reports_attribute_errors() {
+        let dir = std::env::temp_dir().join(format!("fsfy-generator-bad-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lib.rs");
+        let generate = |code: &str| {
+            fs::write(&path, code).unwrap();
+            Generator::new().source(&dir).generate()
+        };
+
+        let serde_errors = generate(
+            "#[derive(Serialize, FSharpDefinition)]\n#[serde(rename_all = \"shouty\")]\nstruct Point { x: i32 }\n",
+        );
+        let fs_errors = generate(
+            "#[derive(Serialize, FSharpDefinition)]\n#[fs(rename_all = \"kebab\")]\n\
+             struct Point { x: i32, #[fs(fs_as = \"1\")] y: i32 }\n",
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        for (result, count) in [(serde_errors, 1), (fs_errors, 2)] {
+            match result {
+                Err(Error::Definition(p, errors)) => {
+                    assert_eq!(p, path);
+                    assert_eq!(errors.len(), count, "{:?}", errors);
+                }
+                other => panic!("expected a definition error, got {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The compile time check the derives emit for each type a definition
+//! refers to: it must derive `FSharpDefinition` or `FSharpify`.
+//!
+//! A type can derive both so they can't implement the same marker trait.
+//! Instead `(&&&Probe::<T>::new()).has_fsharp_definition()` picks the first
+//! method that applies, peeling off a reference each time: the one for
+//! `HasFSharpDefinition` types, then for `FSharpifyTrait` types and, if `T`
+//! has neither, one whose `where` clause fails with a "has no FSharp
+//! definition" error at the field.
+
+use crate::{FSharpifyTrait, HasFSharpDefinition};
+use std::marker::PhantomData;
+
+pub struct Probe<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized> Probe<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Probe(PhantomData)
+    }
+}
+
+pub trait ViaFSharpDefinition {
+    fn has_fsharp_definition(&self) {}
+}
+
+impl<T: ?Sized + HasFSharpDefinition> ViaFSharpDefinition for &&Probe<T> {}
+
+pub trait ViaFSharpify {
+    fn has_fsharp_definition(&self) {}
+}
+
+impl<T: ?Sized + FSharpifyTrait> ViaFSharpify for &Probe<T> {}
+
+pub trait NoFSharpDefinition<T: ?Sized> {
+    fn has_fsharp_definition(&self)
+    where
+        T: HasFSharpDefinition,
+    {
+    }
+}
+
+impl<T: ?Sized> NoFSharpDefinition<T> for Probe<T> {}
@@ -22,12 +22,162 @@ pub use fsharp_definitions_derive::*;
 use serde::ser::Serializer;
 use std::borrow::Cow;
 
+pub mod build;
+#[doc(hidden)]
+pub mod check;
+pub mod organize;
+/// The type model the definitions are rendered from, see [`build::Generator::definitions`].
+pub use fsharp_definitions_internals::model;
+
 /// # Trait implemented by `FSharpify` derive macro.
 ///
 /// Please see documentation at [crates.io](https://crates.io/crates/fsharp-definitions)
 /// or the [README](README/index.html).
+///
+/// ```
+/// use fsharp_definitions::{FSharpify, FSharpifyTrait};
+///
+/// #[derive(FSharpify)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// assert!(Point::fsharp_ify().contains("type Point ="));
+/// ```
+///
+/// The types a definition refers to must derive `FSharpify` or
+/// `FSharpDefinition` too, and `#[serde(flatten)]` ones `FSharpify`:
+///
+/// ```compile_fail
+/// use fsharp_definitions::FSharpify;
+///
+/// struct Point(i32, i32);
+///
+/// #[derive(FSharpify)]
+/// struct Line {
+///     from: Point,
+///     to: Point,
+/// }
+/// ```
 pub trait FSharpifyTrait {
     fn fsharp_ify() -> Cow<'static, str>;
+
+    /// The fields of the FSharp record, one per line, for types that
+    /// `#[serde(flatten)]` this one. `None` if this isn't a record.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use fsharp_definitions::{FSharpify, FSharpifyTrait};
+    ///
+    /// #[derive(Serialize, FSharpify)]
+    /// struct Meta {
+    ///     created: u64,
+    /// }
+    ///
+    /// #[derive(Serialize, FSharpify)]
+    /// struct Doc {
+    ///     title: String,
+    ///     #[serde(flatten)]
+    ///     meta: Meta,
+    /// }
+    ///
+    /// assert!(Meta::fsharp_fields().unwrap().contains("created: uint64;"));
+    /// assert!(Doc::fsharp_ify().contains("    created: uint64;"));
+    /// ```
+    fn fsharp_fields() -> Option<Cow<'static, str>> {
+        None
+    }
+
+    /// The names of the other (non builtin) types the FSharp definition
+    /// refers to, so that definitions can be written in dependency order.
+    ///
+    /// ```
+    /// use fsharp_definitions::{FSharpify, FSharpifyTrait};
+    ///
+    /// #[derive(FSharpify)]
+    /// struct Point(i32, i32);
+    ///
+    /// #[derive(FSharpify)]
+    /// struct Line {
+    ///     from: Point,
+    ///     to: Point,
+    /// }
+    ///
+    /// assert_eq!(Line::fsharp_references(), &["Point"]);
+    /// ```
+    fn fsharp_references() -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// Implemented by the `FSharpDefinition` derive. Both derives check that the
+/// types a definition refers to implement this or [`FSharpifyTrait`], so
+/// forgetting the derive is a compile error at the field instead of FSharp that
+/// refers to an undefined type. See [`check`].
+///
+/// ```
+/// use fsharp_definitions::{FSharpDefinition, FSharpify};
+///
+/// #[derive(FSharpDefinition)]
+/// struct Point(i32, i32);
+///
+/// #[derive(FSharpify)]
+/// struct Line {
+///     from: Point,
+///     to: Point,
+/// }
+///
+/// #[derive(FSharpDefinition, FSharpify)]
+/// struct Path {
+///     lines: Vec<Line>,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use fsharp_definitions::FSharpDefinition;
+///
+/// struct Point(i32, i32);
+///
+/// #[derive(FSharpDefinition)]
+/// struct Line {
+///     from: Point,
+///     to: Point,
+/// }
+/// ```
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no FSharp definition",
+    label = "referenced by a type with a FSharp definition",
+    note = "derive `FSharpDefinition` or `FSharpify` for it, or use `#[fs(fs_type = \"...\")]` or `#[fs(fs_as = \"...\")]` on the field"
+)]
+pub trait HasFSharpDefinition {}
+
+/// Replaces the `(* ♒︎ flatten(Name) *)` markers in `source` with the
+/// `fsharp_fields()` of each flattened type. Used by the `FSharpify` derive.
+#[doc(hidden)]
+pub fn flatten_fields(source: &str, flattened: &[(&str, Option<Cow<'static, str>>)]) -> String {
+    let mut source = source.to_string();
+    for (name, fields) in flattened {
+        let marker = format!("(* ♒︎ flatten({}) *)", name);
+        let fields = match fields {
+            Some(fields) => fields.clone(),
+            None => Cow::Owned(format!("(* TODO: flatten({}): not a record *)", name)),
+        };
+        source = source
+            .lines()
+            .map(|line| match line.find(&marker) {
+                Some(pos) => {
+                    let indent = &line[..line.len() - line.trim_start().len()];
+                    let fields = fields.replace('\n', &format!("\n{}", indent));
+                    format!("{}{}{}", &line[..pos], fields, &line[pos + marker.len()..])
+                }
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    source
 }
 
 /// # String serializer for `u8` byte buffers.
@@ -0,0 +1,553 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Assemble the sections printed by `#[derive(FSharpDefinition)]` into one FSharp file.
+//!
+//! With `FSFY_SHOW_CODE=1` the derive prints `(* ♒︎ section(Name) *)`,
+//! `(* ♒︎ helper(Name) *)` and `(* ♒︎ fields(Name) *)` sections to stderr.
+//! The [`Organizer`] collects them from (any) text, de-duplicates them and writes:
+//!
+//! * the header, followed by `open FSharp.Json` if the definitions need it
+//!   (and the header doesn't already open it),
+//! * the helper sections, sorted by name,
+//! * the type sections in dependency order, ties going alphabetically.
+//!   Types that refer to each other are written as one `type A = ... and B = ...` group.
+//!
+//! `(* ♒︎ flatten(Name) *)` markers are replaced by the `fields` section of `Name`.
+//!
+//! ```
+//! use fsharp_definitions::organize::Organizer;
+//!
+//! let output = "\
+//! (* ♒︎ section(Line) *)
+//! (* ♒︎ references(Point) *)
+//! type Line = { from: Point; to: Point }
+//! (* ♒︎ section end(Line) *)
+//! warning: unused variable
+//! (* ♒︎ section(Point) *)
+//! type Point = { x: int32; y: int32 }
+//! (* ♒︎ section end(Point) *)";
+//!
+//! let mut organizer = Organizer::new();
+//! organizer.read(output);
+//! assert_eq!(
+//!     organizer.finish("module Models"),
+//!     "module Models\ntype Point = { x: int32; y: int32 }\n\ntype Line = { from: Point; to: Point }\n"
+//! );
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+const OPEN_FSHARP_JSON: &str = "open FSharp.Json";
+/// anything in the definitions that needs `open FSharp.Json`
+const FSHARP_JSON_NAMES: &[&str] = &[
+    "JsonUnion",
+    "JsonField",
+    "ITypeTransform",
+    "Json.serialize",
+    "Json.deserialize",
+];
+const NOTE: &str = "// NOTE: ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// a type definition
+    Section,
+    /// shared definitions (e.g. FSharp.Json transforms) which go before all types
+    Helper,
+    /// the record fields that replace `(* ♒︎ flatten(X) *)` markers
+    Fields,
+}
+
+impl Kind {
+    const ALL: [Kind; 3] = [Kind::Section, Kind::Helper, Kind::Fields];
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Section => "section",
+            Kind::Helper => "helper",
+            Kind::Fields => "fields",
+        }
+    }
+}
+
+/// `(* ♒︎ kind(Name) *)` or, with `end`, `(* ♒︎ kind end(Name) *)` anywhere in `line`.
+fn section_marker(line: &str, end: bool) -> Option<(Kind, &str)> {
+    Kind::ALL.iter().find_map(|kind| {
+        let name = marker(
+            line,
+            &format!("{}{}", kind.name(), if end { " end" } else { "" }),
+        )?;
+        Some((*kind, name))
+    })
+}
+
+/// the argument of a `(* ♒︎ what(...) *)` marker in `line`.
+fn marker<'a>(line: &'a str, what: &str) -> Option<&'a str> {
+    let prefix = format!("(* ♒︎ {}(", what);
+    let start = line.find(&prefix)? + prefix.len();
+    let len = line[start..].find(") *)")?;
+    Some(&line[start..start + len])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    source: String,
+    /// the (derived) types this section refers to
+    references: Vec<String>,
+}
+
+impl Section {
+    /// split off the `(* ♒︎ references(A, B) *)` line the derive starts a section with
+    fn new(source: String) -> Self {
+        let mut lines = source.lines();
+        match lines.next().and_then(|line| marker(line, "references")) {
+            Some(names) => Section {
+                references: names.split(", ").map(str::to_string).collect(),
+                source: lines.collect::<Vec<_>>().join("\n"),
+            },
+            None => Section {
+                source,
+                references: vec![],
+            },
+        }
+    }
+}
+
+impl AsRef<str> for Section {
+    fn as_ref(&self) -> &str {
+        &self.source
+    }
+}
+
+/// keep the first definition of `name`, returning a warning if a different one turns up.
+fn insert_once<T: AsRef<str> + PartialEq>(
+    sections: &mut BTreeMap<String, T>,
+    name: &str,
+    section: T,
+) -> Option<String> {
+    match sections.get(name) {
+        Some(existing) if *existing != section => Some(format!(
+            "Found conflicting source defintion for \"{}\"\n\nAlready had:\n-------\n{}\n-------\nBut found alternative definiton:\n-------\n{}\n-------",
+            name,
+            existing.as_ref(),
+            section.as_ref()
+        )),
+        Some(_) => None,
+        None => {
+            sections.insert(name.to_string(), section);
+            None
+        }
+    }
+}
+
+/// Collects sections and writes them out as one FSharp source file.
+#[derive(Debug, Default)]
+pub struct Organizer {
+    keep_notes: bool,
+    helpers: BTreeMap<String, String>,
+    sections: BTreeMap<String, Section>,
+    fields: BTreeMap<String, String>,
+    warnings: Vec<String>,
+}
+
+impl Organizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// keep the `// NOTE: ` lines the derive leaves to explain itself
+    pub fn keep_notes(mut self, keep_notes: bool) -> Self {
+        self.keep_notes = keep_notes;
+        self
+    }
+
+    /// problems found so far, e.g. two different definitions for the same name
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// collect the sections in `output`. Lines outside sections (e.g. compiler messages) are ignored.
+    pub fn read(&mut self, output: &str) {
+        let mut current: Option<(Kind, &str)> = None;
+        let mut lines = vec![];
+        for line in output.lines().map(str::trim_end) {
+            match current {
+                None => {
+                    if let Some(start) = section_marker(line, false) {
+                        current = Some(start);
+                        lines.clear();
+                    }
+                }
+                Some((kind, name)) => {
+                    if let Some(end) = section_marker(line, true) {
+                        if end != (kind, name) {
+                            self.warnings.push(format!(
+                                "Unexpected section end ({}) not matching current section ({})",
+                                end.1, name
+                            ));
+                        }
+                        self.add(kind, name, lines.join("\n"));
+                        current = None;
+                    } else if !line.is_empty()
+                        && (self.keep_notes || !line.trim_start().starts_with(NOTE))
+                    {
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, kind: Kind, name: &str, source: String) {
+        let warning = match kind {
+            Kind::Section => insert_once(&mut self.sections, name, Section::new(source)),
+            Kind::Helper => insert_once(&mut self.helpers, name, source),
+            Kind::Fields => insert_once(&mut self.fields, name, source),
+        };
+        self.warnings.extend(warning);
+    }
+
+    /// the FSharp source: `header` followed by all the definitions.
+    pub fn finish(&mut self, header: &str) -> String {
+        let names = self.sections.keys().cloned().collect::<Vec<_>>();
+        let dependencies = names
+            .iter()
+            .map(|name| {
+                let mut seen = BTreeSet::new();
+                let deps = self
+                    .flatten_references(name, &mut seen)
+                    .into_iter()
+                    .filter(|dep| self.sections.contains_key(dep))
+                    .collect::<Vec<_>>();
+                (name.clone(), deps)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let components = strongly_connected_components(&names, &dependencies);
+
+        let mut groups = vec![];
+        // fsharp needs a type to be defined before it is used
+        for component in topological_order(&names, &dependencies, &components) {
+            let mut sources = vec![];
+            for member in &component {
+                let source = self.sections[member].source.clone();
+                sources.push(self.resolve_flatten(&source, &mut vec![member.clone()]));
+            }
+            let first = &component[0];
+            let recursive = component.len() > 1
+                || (dependencies[first].contains(first) && count_type_definitions(&sources[0]) > 1);
+            groups.push(if recursive {
+                recursive_group(&sources)
+            } else {
+                sources[0].clone()
+            });
+        }
+
+        let definitions = self
+            .helpers
+            .values()
+            .cloned()
+            .chain(groups)
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut out = String::from(header);
+        out.push('\n');
+        let needs_fsharp_json = FSHARP_JSON_NAMES.iter().any(|n| definitions.contains(n));
+        if needs_fsharp_json && !header.contains(OPEN_FSHARP_JSON) {
+            out.push_str(OPEN_FSHARP_JSON);
+            out.push_str("\n\n");
+        }
+        out.push_str(&definitions);
+        out.push('\n');
+        out
+    }
+
+    /// the types a section refers to, including those of the records flattened into it
+    /// (by its own definitions, e.g. an enum's variant records, or by its fields).
+    fn flatten_references(&self, name: &str, seen: &mut BTreeSet<String>) -> BTreeSet<String> {
+        seen.insert(name.to_string());
+        let section = self.sections.get(name);
+        let mut result: BTreeSet<String> = section
+            .map(|s| s.references.iter().cloned().collect())
+            .unwrap_or_default();
+        let source = section.map(|s| s.source.as_str()).unwrap_or("");
+        let fields = self.fields.get(name).map(String::as_str).unwrap_or("");
+        let markers = source
+            .lines()
+            .chain(fields.lines())
+            .filter_map(|line| marker(line, "flatten"));
+        for flattened in markers {
+            if !seen.contains(flattened) {
+                result.extend(self.flatten_references(flattened, seen));
+            }
+        }
+        result
+    }
+
+    /// replace `(* ♒︎ flatten(X) *)` markers with the (indented) fields of `X`.
+    fn resolve_flatten(&mut self, source: &str, stack: &mut Vec<String>) -> String {
+        source
+            .lines()
+            .map(|line| {
+                let name = match marker(line, "flatten") {
+                    Some(name) => name,
+                    None => return line.to_string(),
+                };
+                let replacement = match self.fields.get(name).cloned() {
+                    Some(fields) if !stack.iter().any(|s| s == name) => {
+                        stack.push(name.to_string());
+                        let fields = self.resolve_flatten(&fields, stack);
+                        stack.pop();
+                        fields
+                    }
+                    _ => {
+                        self.warnings.push(format!(
+                            "Cannot flatten \"{}\" into \"{}\": no record fields found",
+                            name, stack[0]
+                        ));
+                        format!("(* TODO: flatten({}): not a record *)", name)
+                    }
+                };
+                let indent = &line[..line.len() - line.trim_start().len()];
+                line.replacen(
+                    &format!("(* ♒︎ flatten({}) *)", name),
+                    &replacement.replace('\n', &format!("\n{}", indent)),
+                    1,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Tarjan's algorithm: each name's (sorted) strongly connected component.
+fn strongly_connected_components(
+    names: &[String],
+    dependencies: &BTreeMap<String, Vec<String>>,
+) -> BTreeMap<String, Vec<String>> {
+    #[derive(Default)]
+    struct Tarjan<'a> {
+        next_index: usize,
+        index: BTreeMap<&'a str, usize>,
+        low_link: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        components: BTreeMap<String, Vec<String>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, name: &'a str, dependencies: &'a BTreeMap<String, Vec<String>>) {
+            self.index.insert(name, self.next_index);
+            self.low_link.insert(name, self.next_index);
+            self.next_index += 1;
+            self.stack.push(name);
+            self.on_stack.insert(name);
+            for dep in &dependencies[name] {
+                let dep = dep.as_str();
+                if !self.index.contains_key(dep) {
+                    self.visit(dep, dependencies);
+                    let low = self.low_link[name].min(self.low_link[dep]);
+                    self.low_link.insert(name, low);
+                } else if self.on_stack.contains(dep) {
+                    let low = self.low_link[name].min(self.index[dep]);
+                    self.low_link.insert(name, low);
+                }
+            }
+            if self.low_link[name] == self.index[name] {
+                let mut component = vec![];
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member.to_string());
+                    if member == name {
+                        break;
+                    }
+                }
+                component.sort();
+                for member in &component {
+                    self.components.insert(member.clone(), component.clone());
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan::default();
+    for name in names {
+        if !tarjan.index.contains_key(name.as_str()) {
+            tarjan.visit(name, dependencies);
+        }
+    }
+    tarjan.components
+}
+
+/// Order the components so each comes after the components it depends on.
+/// Of the components that are ready, the one whose first name sorts first
+/// goes next so the output only changes when the types do.
+fn topological_order(
+    names: &[String],
+    dependencies: &BTreeMap<String, Vec<String>>,
+    components: &BTreeMap<String, Vec<String>>,
+) -> Vec<Vec<String>> {
+    // components keyed by their first (smallest) name
+    let mut waiting_on = BTreeMap::new();
+    for name in names {
+        let component = &components[name];
+        let deps = component
+            .iter()
+            .flat_map(|member| &dependencies[member])
+            .map(|dep| components[dep][0].clone())
+            .filter(|dep| *dep != component[0])
+            .collect::<BTreeSet<_>>();
+        waiting_on.insert(component[0].clone(), deps);
+    }
+    let mut order = vec![];
+    while let Some(next) = waiting_on
+        .iter()
+        .find(|(_, deps)| deps.is_empty())
+        .map(|(name, _)| name.clone())
+    {
+        waiting_on.remove(&next);
+        for deps in waiting_on.values_mut() {
+            deps.remove(&next);
+        }
+        order.push(components[&next].clone());
+    }
+    order
+}
+
+fn is_type_definition(line: &str) -> bool {
+    line.starts_with("type ")
+}
+
+fn count_type_definitions(source: &str) -> usize {
+    source.lines().filter(|l| is_type_definition(l)).count()
+}
+
+/// Join mutually recursive sections into one `type A = ... and B = ...` group.
+/// Attributes move after the `and` and modules (e.g. decoders) follow the group.
+fn recursive_group(sources: &[String]) -> String {
+    let mut definitions = vec![];
+    let mut modules = vec![];
+    for source in sources {
+        let lines = source.lines().collect::<Vec<_>>();
+        match lines.iter().position(|line| line.starts_with("module ")) {
+            Some(start) => {
+                definitions.extend_from_slice(&lines[..start]);
+                modules.push(lines[start..].join("\n"));
+            }
+            None => definitions.extend(lines),
+        }
+    }
+
+    let mut group: Vec<String> = vec![];
+    let mut attributes: Vec<&str> = vec![];
+    let mut first = true;
+    for line in definitions {
+        if line.starts_with("[<") && line.ends_with(">]") {
+            attributes.push(line);
+            continue;
+        }
+        if is_type_definition(line) && !first {
+            let mut and = vec!["and"];
+            and.append(&mut attributes);
+            and.push(&line["type ".len()..]);
+            group.push(and.join(" "));
+        } else {
+            first = first && !is_type_definition(line);
+            group.extend(attributes.drain(..).map(str::to_string));
+            group.push(line.to_string());
+        }
+    }
+    group.extend(attributes.drain(..).map(str::to_string));
+
+    let mut out = vec![group.join("\n")];
+    out.extend(modules);
+    out.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(name: &str, references: &[&str], source: &str) -> String {
+        let references = if references.is_empty() {
+            String::new()
+        } else {
+            format!("(* ♒︎ references({}) *)\n", references.join(", "))
+        };
+        format!(
+            "\n(* ♒︎ section({}) *)\n{}{}\n(* ♒︎ section end({}) *)\n",
+            name, references, source, name
+        )
+    }
+
+    #[test]
+    fn mutually_recursive_types_are_grouped() {
+        let mut output = String::from("   Compiling models v0.1.0\n");
+        output.push_str(&section(
+            "Stmt",
+            &["Expr"],
+            "// NOTE: struct variant ♛\n[<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>]\ntype Stmt =\n  | Expr of Expr",
+        ));
+        output.push_str(&section(
+            "Expr",
+            &["Stmt"],
+            "type ExprBlock = Stmt list\ntype Expr =\n  | Block of ExprBlock\nmodule Expr =\n  let x = 1",
+        ));
+        output.push_str(&section("Alpha", &[], "type Alpha = int32"));
+        let mut organizer = Organizer::new();
+        organizer.read(&output);
+        assert_eq!(
+            organizer.finish("// head"),
+            "// head\nopen FSharp.Json\n\ntype Alpha = int32\n\ntype ExprBlock = Stmt list\nand Expr =\n  | Block of ExprBlock\nand [<JsonUnion(Mode = UnionMode.CaseKeyAsFieldName)>] Stmt =\n  | Expr of Expr\n\nmodule Expr =\n  let x = 1\n"
+        );
+        assert!(organizer.warnings().is_empty());
+    }
+
+    #[test]
+    fn flatten_and_conflicts() {
+        let mut output = section(
+            "Doc",
+            &[],
+            "type Doc ={\n    title: string;\n    (* ♒︎ flatten(Meta) *) }",
+        );
+        output.push_str(&section(
+            "Meta",
+            &["Date"],
+            "type Meta ={\n    created: Date; }",
+        ));
+        output.push_str("(* ♒︎ fields(Meta) *)\ncreated: Date;\n(* ♒︎ fields end(Meta) *)\n");
+        output.push_str(&section("Date", &[], "type Date = string"));
+        output.push_str(&section("Date", &[], "type Date = int64"));
+        let mut organizer = Organizer::new();
+        organizer.read(&output);
+        assert_eq!(
+            organizer.finish(""),
+            "\ntype Date = string\n\ntype Doc ={\n    title: string;\n    created: Date; }\n\ntype Meta ={\n    created: Date; }\n"
+        );
+        assert_eq!(organizer.warnings().len(), 1);
+        assert!(
+            organizer.warnings()[0].starts_with("Found conflicting source defintion for \"Date\"")
+        );
+        // the record of an enum variant flattens `M`, which needs `Z` first
+        let mut output = section(
+            "A",
+            &[],
+            "type AV ={\n    (* ♒︎ flatten(M) *)\n    y: int32; }\ntype A =\n  | V of AV",
+        );
+        output.push_str(&section("M", &["Z"], "type M ={\n    z: Z; }"));
+        output.push_str("(* ♒︎ fields(M) *)\nz: Z;\n(* ♒︎ fields end(M) *)\n");
+        output.push_str(&section("Z", &[], "type Z = string"));
+        let mut organizer = Organizer::new();
+        organizer.read(&output);
+        assert_eq!(
+            organizer.finish(""),
+            "\ntype Z = string\n\ntype AV ={\n    z: Z;\n    y: int32; }\ntype A =\n  | V of AV\n\ntype M ={\n    z: Z; }\n"
+        );
+    }
+}